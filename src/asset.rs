@@ -13,6 +13,7 @@ use std::collections::HashMap;
 use std::io::{self, Error, ErrorKind};
 use std::io::prelude::*;
 
+use crate::asset::message::MessageId;
 use crate::graphics::EPoint;
 use crate::graphics::geometry::hex::Direction;
 
@@ -93,7 +94,14 @@ pub enum SceneryKind {
     Misc = 0x5,
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Primitive)]
+impl SceneryKind {
+    /// Returns ID of the kind name message in `proto.msg`.
+    pub fn message_id(self) -> MessageId {
+        200 + self as MessageId
+    }
+}
+
+#[derive(Clone, Copy, Debug, Enum, Eq, PartialEq, Ord, PartialOrd, Primitive)]
 pub enum ItemKind {
     Armor = 0x0,
     Container = 0x1,
@@ -104,6 +112,13 @@ pub enum ItemKind {
     Key = 0x6,
 }
 
+impl ItemKind {
+    /// Returns ID of the kind name message in `proto.msg`.
+    pub fn message_id(self) -> MessageId {
+        150 + self as MessageId
+    }
+}
+
 #[derive(Clone, Copy, Debug, Enum, Eq, PartialEq, Ord, PartialOrd, Primitive)]
 pub enum CritterAnim {
     // basic animations  0-19
@@ -211,6 +226,60 @@ pub enum Material {
     Leather     = 7,
 }
 
+impl Material {
+    /// Returns default sound ID for objects of this material. Sound IDs are character codes
+    /// used to build SFX file names.
+    pub fn sound_id(self) -> u8 {
+        match self {
+            Self::Glass => b'G',
+            Self::Metal => b'M',
+            Self::Plastic => b'P',
+            Self::Wood => b'W',
+            Self::Dirt => b'D',
+            Self::Stone => b'S',
+            Self::Cement => b'C',
+            Self::Leather => b'L',
+        }
+    }
+
+    /// Returns ID of the material name message in `proto.msg`.
+    pub fn message_id(self) -> MessageId {
+        100 + self as MessageId
+    }
+
+    /// Returns English name of the material. See `message_id()` for the localized name.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Glass => "Glass",
+            Self::Metal => "Metal",
+            Self::Plastic => "Plastic",
+            Self::Wood => "Wood",
+            Self::Dirt => "Dirt",
+            Self::Stone => "Stone",
+            Self::Cement => "Cement",
+            Self::Leather => "Leather",
+        }
+    }
+
+    pub fn properties(self) -> MaterialProps {
+        MaterialProps {
+            sound_id: self.sound_id(),
+            // In the original movement is never blocked by the material, only by objects.
+            impassable: false,
+        }
+    }
+}
+
+/// Material semantics shared by floor tiles, walls and items. See `Material::properties()`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MaterialProps {
+    /// See `Material::sound_id()`.
+    pub sound_id: u8,
+
+    /// Whether tiles of this material can't be walked on.
+    pub impassable: bool,
+}
+
 #[derive(Clone, Copy, Debug, Enum, Eq, PartialEq, Primitive)]
 pub enum DamageKind {
     Melee       = 0,
@@ -224,6 +293,17 @@ pub enum DamageKind {
     Poison      = 100001,
 }
 
+impl DamageKind {
+    /// Returns ID of the damage kind name message in `proto.msg`.
+    /// Returns `None` for `Radiation` and `Poison` which don't have one.
+    pub fn message_id(self) -> Option<MessageId> {
+        match self {
+            Self::Radiation | Self::Poison => None,
+            _ => Some(250 + self as MessageId),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Enum, Eq, PartialEq, Primitive)]
 pub enum Stat {
     Strength = 0x0,
@@ -269,6 +349,13 @@ pub enum Stat {
     CurrentRad = 0x25,
 }
 
+impl Stat {
+    /// Returns ID of the stat name message in `stat.msg`. Stats aren't labeled in `proto.msg`.
+    pub fn message_id(self) -> MessageId {
+        100 + self as MessageId
+    }
+}
+
 #[derive(Clone, Copy, Debug, Enum, Eq, PartialEq, Primitive)]
 pub enum Perk {
     BonusAwareness = 0x0,
@@ -562,6 +649,43 @@ pub enum AttackKind {
     FireContinuous  = 8,
 }
 
+impl AttackKind {
+    /// Whether the attack can hit targets beyond the adjacent tiles.
+    pub fn is_ranged(self) -> bool {
+        match self {
+            | Self::Throw
+            | Self::FireSingle
+            | Self::FireBurst
+            | Self::FireContinuous
+            => true,
+            | Self::Stand
+            | Self::Punch
+            | Self::Kick
+            | Self::Swing
+            | Self::Thrust
+            => false,
+        }
+    }
+
+    /// Whether the attack consumes ammo. Note thrown weapons are ranged but consume the weapon
+    /// itself instead.
+    pub fn uses_ammo(self) -> bool {
+        match self {
+            | Self::FireSingle
+            | Self::FireBurst
+            | Self::FireContinuous
+            => true,
+            | Self::Stand
+            | Self::Punch
+            | Self::Kick
+            | Self::Swing
+            | Self::Thrust
+            | Self::Throw
+            => false,
+        }
+    }
+}
+
 pub struct LstEntry {
     pub fields: Vec<String>,
 }
@@ -670,6 +794,69 @@ mod test {
     use super::*;
     use std::io::{Cursor, BufReader};
 
+    use crate::util::EnumExt;
+
+    #[test]
+    fn material_properties() {
+        for m in Material::iter() {
+            let props = m.properties();
+            assert_eq!(props.sound_id, m.sound_id());
+            assert!(!props.impassable);
+        }
+    }
+
+    #[test]
+    fn material_name() {
+        let names: Vec<_> = Material::iter().map(|m| m.name()).collect();
+        assert_eq!(names[0], "Glass");
+        for (i, n) in names.iter().enumerate() {
+            assert!(!n.is_empty());
+            assert!(!names[i + 1..].contains(n));
+        }
+    }
+
+    #[test]
+    fn message_id() {
+        assert_eq!(Material::Glass.message_id(), 100);
+        assert_eq!(Material::Leather.message_id(), 107);
+        assert_eq!(ItemKind::Armor.message_id(), 150);
+        assert_eq!(ItemKind::Key.message_id(), 156);
+        assert_eq!(SceneryKind::Door.message_id(), 200);
+        assert_eq!(SceneryKind::Misc.message_id(), 205);
+        assert_eq!(DamageKind::Melee.message_id(), Some(250));
+        assert_eq!(DamageKind::Explosion.message_id(), Some(256));
+        assert_eq!(DamageKind::Radiation.message_id(), None);
+        assert_eq!(Stat::Strength.message_id(), 100);
+        assert_eq!(Stat::Luck.message_id(), 106);
+        assert_eq!(Stat::CurrentRad.message_id(), 137);
+    }
+
+    #[test]
+    fn attack_kind() {
+        assert!(AttackKind::Throw.is_ranged());
+        assert!(!AttackKind::Throw.uses_ammo());
+        assert!(AttackKind::FireBurst.is_ranged());
+        assert!(AttackKind::FireBurst.uses_ammo());
+        assert!(!AttackKind::Punch.is_ranged());
+        assert!(!AttackKind::Punch.uses_ammo());
+    }
+
+    #[test]
+    fn weapon_kind_anim_code() {
+        assert_eq!(WeaponKind::Unarmed.anim_code(), 'a');
+        assert_eq!(WeaponKind::Knife.anim_code(), 'd');
+        assert_eq!(WeaponKind::Pistol.anim_code(), 'h');
+        assert_eq!(WeaponKind::Rifle.anim_code(), 'j');
+        assert_eq!(WeaponKind::Launcher.anim_code(), 'm');
+    }
+
+    #[test]
+    fn material_sound_id() {
+        assert_eq!(Material::Metal.sound_id(), b'M');
+        assert_eq!(Material::Dirt.sound_id(), b'D');
+        assert_ne!(Material::Metal.sound_id(), Material::Dirt.sound_id());
+    }
+
     #[test]
     fn read_game_global_vars_() {
         let s = "
@@ -1,98 +1,652 @@
 mod db;
 pub mod id;
+#[cfg(feature = "png-export")]
+pub mod png;
 
 use byteorder::{BigEndian, ReadBytesExt};
 use enum_map::EnumMap;
-use std::io::{self, prelude::*};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::borrow::Cow;
+use std::cmp;
+use std::io::{self, Error, ErrorKind, prelude::*};
 
 pub use id::FrameId;
 pub use db::FrameDb;
 
-use crate::graphics::Point;
+use crate::graphics::{Point, Rect};
+use crate::graphics::color::palette::Palette;
 use crate::graphics::geometry::hex::Direction;
 use crate::graphics::render::TextureFactory;
 use crate::graphics::sprite::*;
 use crate::util::EnumExt;
 
-pub fn read_frm(rd: &mut impl Read, texture_factory: &TextureFactory) -> io::Result<FrameSet> {
-    let _version = rd.read_u32::<BigEndian>()?;
+/// Per-frame metadata from FRM file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FrmFrameInfo {
+    pub width: u16,
+    pub height: u16,
+    pub shift: Point,
 
-    let fps = rd.read_u16::<BigEndian>()?;
-    let fps = if fps == 0 {
-        10
-    } else {
-        fps
-    };
+    /// Offset of the frame pixels from the start of the FRM frame data.
+    pub pixels_offset: u32,
+}
 
-    let action_frame = rd.read_u16::<BigEndian>()?;
-    let frames_per_direction = rd.read_u16::<BigEndian>()? as usize;
-    assert!(frames_per_direction > 0);
+/// Per-direction metadata from FRM file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FrmDirectionInfo {
+    pub center: Point,
 
-    let mut centers_x = EnumMap::new();
-    for dir in Direction::iter() {
-        centers_x[dir] = rd.read_i16::<BigEndian>()? as i32;
+    /// Offset of the direction frames from the start of the FRM frame data.
+    /// Several directions can share the same frames.
+    pub offset: u32,
+
+    pub frames: Vec<FrmFrameInfo>,
+}
+
+/// FRM file metadata. Allows querying frame properties without decoding the frame pixels.
+#[derive(Clone, Debug)]
+pub struct FrmHeader {
+    pub fps: u16,
+    pub action_frame: u16,
+    pub directions: EnumMap<Direction, FrmDirectionInfo>,
+}
+
+impl FrmHeader {
+    /// Size of the FRM header preceding the frame data.
+    pub const LEN: u32 = 4 + 2 * 3 + 2 * 6 * 2 + 4 * 6 + 4;
+
+    /// Size of the header preceding pixels of each frame.
+    pub const FRAME_HEADER_LEN: u32 = 2 * 2 + 4 + 2 * 2;
+
+    /// Reads FRM metadata skipping over the pixel data.
+    pub fn read(rd: &mut impl Read) -> io::Result<Self> {
+        let _version = rd.read_u32::<BigEndian>()?;
+
+        let fps = rd.read_u16::<BigEndian>()?;
+        let fps = if fps == 0 {
+            10
+        } else {
+            fps
+        };
+
+        let action_frame = rd.read_u16::<BigEndian>()?;
+        let frames_per_direction = rd.read_u16::<BigEndian>()? as usize;
+        if frames_per_direction == 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "FRM has no frames"));
+        }
+
+        let mut centers_x = EnumMap::new();
+        for dir in Direction::iter() {
+            centers_x[dir] = rd.read_i16::<BigEndian>()? as i32;
+        }
+        let mut centers_y = EnumMap::new();
+        for dir in Direction::iter() {
+            centers_y[dir] = rd.read_i16::<BigEndian>()? as i32;
+        }
+
+        let mut offsets = EnumMap::new();
+        for dir in Direction::iter() {
+            offsets[dir] = rd.read_u32::<BigEndian>()?;
+        }
+
+        let _data_len = rd.read_u32::<BigEndian>()?;
+
+        let mut pos = 0;
+        let mut frame_lists: EnumMap<Direction, Option<Vec<FrmFrameInfo>>> = EnumMap::new();
+        for dir in Direction::iter() {
+            let offset = offsets[dir];
+            let already_loaded_dir = Direction::iter()
+                .take_while(|&d| d != dir)
+                .find(|&d| offsets[d] == offset);
+            if let Some(already_loaded_dir) = already_loaded_dir {
+                frame_lists[dir] = frame_lists[already_loaded_dir].clone();
+                continue;
+            }
+
+            let mut frames = Vec::with_capacity(frames_per_direction);
+            for _ in 0..frames_per_direction {
+                let width = rd.read_u16::<BigEndian>()?;
+                let height = rd.read_u16::<BigEndian>()?;
+                let _len = rd.read_u32::<BigEndian>()?;
+                let shift = Point::new(
+                    rd.read_i16::<BigEndian>()? as i32,
+                    rd.read_i16::<BigEndian>()? as i32,
+                );
+                pos += Self::FRAME_HEADER_LEN;
+
+                let len = width as u64 * height as u64;
+                if io::copy(&mut rd.take(len), &mut io::sink())? != len {
+                    return Err(Error::new(ErrorKind::UnexpectedEof, "truncated FRM frame"));
+                }
+
+                frames.push(FrmFrameInfo {
+                    width,
+                    height,
+                    shift,
+                    pixels_offset: pos,
+                });
+                pos += len as u32;
+            }
+            frame_lists[dir] = Some(frames);
+        }
+
+        Ok(Self {
+            fps,
+            action_frame,
+            directions: EnumMap::from(|dir| FrmDirectionInfo {
+                center: Point::new(centers_x[dir], centers_y[dir]),
+                offset: offsets[dir],
+                frames: frame_lists[dir].take().unwrap(),
+            }),
+        })
     }
-    let mut centers_y = EnumMap::new();
-    for dir in Direction::iter() {
-        centers_y[dir] = rd.read_i16::<BigEndian>()? as i32;
+
+    pub fn frame_count(&self, direction: Direction) -> usize {
+        self.directions[direction].frames.len()
     }
 
-    let mut frame_offsets = EnumMap::new();
-    for dir in Direction::iter() {
-        frame_offsets[dir] = rd.read_u32::<BigEndian>()?;
+    pub fn frame(&self, direction: Direction, frame: usize) -> Option<&FrmFrameInfo> {
+        self.directions[direction].frames.get(frame)
     }
 
-    let _data_len = rd.read_u32::<BigEndian>()?;
+    /// Returns offset of the `direction` frames from the drawing position. The bottom center
+    /// point of a frame is placed at the drawing position plus this offset.
+    pub fn anchor(&self, direction: Direction) -> Point {
+        self.directions[direction].center
+    }
 
-    let mut loaded_offsets: EnumMap<Direction, Option<u32>> = EnumMap::new();
-    let mut frame_lists: EnumMap<Direction, Option<FrameList>> = EnumMap::new();
-    for dir in Direction::iter() {
-        let offset = frame_offsets[dir];
-        let already_loaded_dir = loaded_offsets
-            .iter()
-            .filter_map(|(d, o)| o.filter(|&o| o == offset).map(|_| d))
-            .next();
-        if let Some(already_loaded_dir) = already_loaded_dir {
-            frame_lists[dir] = frame_lists[already_loaded_dir].clone();
-            continue;
+    /// Returns the direction that stores frames for the `requested` direction and whether
+    /// the frames must be mirrored horizontally. Directions having no frames or only empty frames
+    /// are considered missing and are substituted with the horizontally mirrored direction
+    /// if it's present.
+    pub fn resolve_direction(&self, requested: Direction) -> (Direction, bool) {
+        let is_present = |dir: Direction| self.directions[dir].frames.iter()
+            .any(|f| f.width > 0 && f.height > 0);
+        if is_present(requested) {
+            return (requested, false);
+        }
+        let mirrored = requested.mirror_x();
+        if is_present(mirrored) {
+            (mirrored, true)
+        } else {
+            (requested, false)
+        }
+    }
+
+    /// Returns `(width, height)` of the frame.
+    pub fn frame_size(&self, direction: Direction, frame: usize) -> Option<(u16, u16)> {
+        self.frame(direction, frame).map(|f| (f.width, f.height))
+    }
+
+    /// Returns `(width, height)` of the smallest size that fits any frame of the `direction`.
+    pub fn direction_bounds(&self, direction: Direction) -> (u16, u16) {
+        self.directions[direction].frames.iter()
+            .fold((0, 0), |(w, h), f| (w.max(f.width), h.max(f.height)))
+    }
+
+    /// Sum of frame shifts from the first frame to `up_to_frame` inclusive.
+    pub fn accumulated_offset(&self, direction: Direction, up_to_frame: usize) -> Point {
+        self.directions[direction].frames.iter()
+            .take(up_to_frame + 1)
+            .fold(Point::new(0, 0), |r, f| r + f.shift)
+    }
+}
+
+/// Decoded FRM frame.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FrmFrame {
+    pub width: u16,
+    pub height: u16,
+    pub shift: Point,
+
+    /// Color indices, row by row.
+    pub pixels: Box<[u8]>,
+}
+
+/// FRM file with frames decoded on demand. The file data is either owned (see `Frm::read()`) or
+/// borrowed (see `parse_frm()`).
+#[derive(Clone, Debug)]
+pub struct Frm<'a> {
+    header: FrmHeader,
+    data: Cow<'a, [u8]>,
+}
+
+impl Frm<'static> {
+    pub fn read(rd: &mut impl Read) -> io::Result<Self> {
+        let mut data = Vec::new();
+        rd.read_to_end(&mut data)?;
+        let header = FrmHeader::read(&mut &data[..])?;
+        Ok(Self {
+            header,
+            data: data.into(),
+        })
+    }
+}
+
+impl<'a> Frm<'a> {
+    pub fn header(&self) -> &FrmHeader {
+        &self.header
+    }
+
+    /// Returns color indices of the `frame` of the `direction` row by row without copying them.
+    /// Returns `None` if `frame` is out of range.
+    pub fn frame_pixels(&self, direction: Direction, frame: usize) -> Option<&[u8]> {
+        let info = self.header.frame(direction, frame)?;
+        let start = (FrmHeader::LEN + info.pixels_offset) as usize;
+        let len = info.width as usize * info.height as usize;
+        self.data.get(start..start + len)
+    }
+
+    /// Decodes the `frame` of the `direction`.
+    /// Returns `None` if `frame` is out of range.
+    pub fn decode_frame(&self, direction: Direction, frame: usize) -> Option<FrmFrame> {
+        let info = self.header.frame(direction, frame)?;
+        let pixels = self.frame_pixels(direction, frame)?;
+        Some(FrmFrame {
+            width: info.width,
+            height: info.height,
+            shift: info.shift,
+            pixels: pixels.into(),
+        })
+    }
+
+    /// Decodes all frames of the `direction` in frame order.
+    #[cfg(not(feature = "parallel"))]
+    pub fn decode_all_frames(&self, direction: Direction) -> Vec<FrmFrame> {
+        (0..self.header.frame_count(direction))
+            // Pixel data of all frames is validated while reading the header.
+            .map(|i| self.decode_frame(direction, i).unwrap())
+            .collect()
+    }
+
+    /// Decodes all frames of the `direction` in frame order. The frames are decoded on multiple
+    /// threads.
+    #[cfg(feature = "parallel")]
+    pub fn decode_all_frames(&self, direction: Direction) -> Vec<FrmFrame> {
+        (0..self.header.frame_count(direction)).into_par_iter()
+            // Pixel data of all frames is validated while reading the header.
+            .map(|i| self.decode_frame(direction, i).unwrap())
+            .collect()
+    }
+
+    /// Packs frames of all directions into a single RGBA image row by row. Returns
+    /// `(pixels, width, height, frame_rects)` where `frame_rects` are locations of the frames in
+    /// the image in direction and then frame order. Directions sharing frames have the same rects.
+    /// Color index 0 is transparent.
+    pub fn pack_atlas(&self, palette: &Palette) -> (Vec<u8>, u32, u32, Vec<Rect>) {
+        let frames: Vec<(Direction, usize, &FrmFrameInfo)> = Direction::iter()
+            .flat_map(|dir| self.header.directions[dir].frames.iter()
+                .enumerate()
+                .map(move |(i, f)| (dir, i, f)))
+            .collect();
+
+        // Unique frames are identified by the pixels offset.
+        let mut unique: Vec<(Direction, usize, &FrmFrameInfo)> = Vec::new();
+        for &(dir, i, f) in &frames {
+            if !unique.iter().any(|u| u.2.pixels_offset == f.pixels_offset) {
+                unique.push((dir, i, f));
+            }
+        }
+
+        // Shelf packing: frames sorted by height are placed left to right in rows.
+        let area: u64 = unique.iter().map(|u| u.2.width as u64 * u.2.height as u64).sum();
+        let max_width = unique.iter().map(|u| u.2.width as u32).max().unwrap_or(0);
+        let width = cmp::max(max_width, ((area as f64).sqrt().ceil() as u32).next_power_of_two());
+        let mut order: Vec<usize> = (0..unique.len()).collect();
+        order.sort_by_key(|&i| cmp::Reverse(unique[i].2.height));
+        let mut unique_rects = vec![Rect::empty(); unique.len()];
+        let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+        for i in order {
+            let f = unique[i].2;
+            if x + f.width as u32 > width {
+                x = 0;
+                y += shelf_height;
+                shelf_height = 0;
+            }
+            unique_rects[i] = Rect::with_size(x as i32, y as i32, f.width as i32, f.height as i32);
+            x += f.width as u32;
+            shelf_height = cmp::max(shelf_height, f.height as u32);
+        }
+        let height = y + shelf_height;
+
+        let mut pixels = vec![0; width as usize * height as usize * 4];
+        for (&(dir, frame, f), rect) in unique.iter().zip(&unique_rects) {
+            // Pixel data of all frames is validated while reading the header.
+            let src = self.frame_pixels(dir, frame).unwrap();
+            for (row_idx, row) in src.chunks(cmp::max(f.width as usize, 1)).enumerate() {
+                let dst_start = ((rect.top as usize + row_idx) * width as usize
+                    + rect.left as usize) * 4;
+                for (i, &color_idx) in row.iter().enumerate() {
+                    if color_idx != 0 {
+                        let rgb = palette.rgb24(color_idx);
+                        pixels[dst_start + i * 4..dst_start + i * 4 + 4]
+                            .copy_from_slice(&[rgb.r(), rgb.g(), rgb.b(), 0xff]);
+                    }
+                }
+            }
         }
 
-        loaded_offsets[dir] = Some(offset);
+        let rects = frames.iter()
+            .map(|&(_, _, f)| {
+                let i = unique.iter().position(|u| u.2.pixels_offset == f.pixels_offset).unwrap();
+                unique_rects[i]
+            })
+            .collect();
 
-        let mut frames = Vec::with_capacity(frames_per_direction);
-        for _ in 0..frames_per_direction {
-            let width = rd.read_i16::<BigEndian>()? as i32;
-            let height = rd.read_i16::<BigEndian>()? as i32;
-            let _len = rd.read_u32::<BigEndian>()?;
-            let shift = Point::new(
-                rd.read_i16::<BigEndian>()? as i32,
-                rd.read_i16::<BigEndian>()? as i32,
-            );
+        (pixels, width, height, rects)
+    }
+}
 
-            let len = (width * height) as usize;
-            let mut pixels = vec![0; len].into_boxed_slice();
-            rd.read_exact(&mut pixels)?;
+/// Parses FRM file from the `data` without copying it. Frames are decoded on demand from `data`.
+pub fn parse_frm(data: &[u8]) -> io::Result<Frm<'_>> {
+    let header = FrmHeader::read(&mut &data[..])?;
+    Ok(Frm {
+        header,
+        data: data.into(),
+    })
+}
 
-            let mask = Mask::new(width, &pixels);
-            let texture = texture_factory.new_texture(width, height, pixels);
+pub fn read_frm(rd: &mut impl Read, texture_factory: &TextureFactory) -> io::Result<FrameSet> {
+    let frm = Frm::read(rd)?;
+    let header = frm.header();
 
-            frames.push(Frame {
-                shift,
-                width,
-                height,
-                texture,
-                mask,
-            });
+    let mut frame_lists: EnumMap<Direction, Option<FrameList>> = EnumMap::new();
+    for dir in Direction::iter() {
+        let offset = header.directions[dir].offset;
+        let already_loaded_dir = Direction::iter()
+            .take_while(|&d| d != dir)
+            .find(|&d| header.directions[d].offset == offset);
+        if let Some(already_loaded_dir) = already_loaded_dir {
+            frame_lists[dir] = frame_lists[already_loaded_dir].clone();
+            continue;
         }
+
+        let frames = frm.decode_all_frames(dir).into_iter()
+            .map(|f| {
+                let width = f.width as i32;
+                let height = f.height as i32;
+                let mask = Mask::new(width, &f.pixels);
+                let texture = texture_factory.new_texture(width, height, f.pixels);
+                Frame {
+                    shift: f.shift,
+                    width,
+                    height,
+                    texture,
+                    mask,
+                }
+            })
+            .collect();
         frame_lists[dir] = Some(FrameList {
-            center: Point::new(centers_x[dir], centers_y[dir]),
+            center: header.directions[dir].center,
             frames,
         });
     }
 
     Ok(FrameSet {
-        fps,
-        action_frame,
+        fps: header.fps,
+        action_frame: header.action_frame,
         frame_lists: EnumMap::from(|k| frame_lists[k].take().unwrap()),
     })
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use byteorder::WriteBytesExt;
+
+    use super::*;
+
+    /// Frame as `(width, height, shift, pixels)`.
+    pub type TestFrame = (u16, u16, (i16, i16), Vec<u8>);
+
+    /// Builds FRM file with `frames` for each distinct direction of `dirs`. Directions with equal
+    /// `dirs` values share the frames.
+    pub fn make_frm(dirs: [usize; 6], frames: &[Vec<TestFrame>]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut offsets = Vec::new();
+        for dir_frames in frames {
+            offsets.push(data.len() as u32);
+            for (width, height, (shift_x, shift_y), pixels) in dir_frames {
+                assert_eq!(pixels.len(), *width as usize * *height as usize);
+                data.write_u16::<BigEndian>(*width).unwrap();
+                data.write_u16::<BigEndian>(*height).unwrap();
+                data.write_u32::<BigEndian>(pixels.len() as u32).unwrap();
+                data.write_i16::<BigEndian>(*shift_x).unwrap();
+                data.write_i16::<BigEndian>(*shift_y).unwrap();
+                data.extend_from_slice(pixels);
+            }
+        }
+
+        let mut r = Vec::new();
+        r.write_u32::<BigEndian>(4).unwrap();
+        r.write_u16::<BigEndian>(12).unwrap();
+        r.write_u16::<BigEndian>(1).unwrap();
+        r.write_u16::<BigEndian>(frames[0].len() as u16).unwrap();
+        for i in 0..6 {
+            r.write_i16::<BigEndian>(i).unwrap();
+        }
+        for i in 0..6 {
+            r.write_i16::<BigEndian>(-i).unwrap();
+        }
+        for &dir in &dirs {
+            r.write_u32::<BigEndian>(offsets[dir]).unwrap();
+        }
+        r.write_u32::<BigEndian>(data.len() as u32).unwrap();
+        assert_eq!(r.len() as u32, FrmHeader::LEN);
+        r.extend(data);
+        r
+    }
+
+    pub fn sample_frm() -> Vec<u8> {
+        make_frm([0, 1, 1, 1, 1, 1], &[
+            vec![
+                (2, 3, (0, 0), vec![1, 2, 3, 4, 5, 6]),
+                (1, 1, (1, -1), vec![7]),
+            ],
+            vec![
+                (3, 1, (0, 1), vec![0, 8, 9]),
+                (2, 2, (-2, 0), vec![10, 11, 12, 13]),
+            ],
+        ])
+    }
+
+    #[test]
+    fn read_header() {
+        let frm = sample_frm();
+        let h = FrmHeader::read(&mut &frm[..]).unwrap();
+
+        assert_eq!(h.fps, 12);
+        assert_eq!(h.action_frame, 1);
+        assert_eq!(h.anchor(Direction::NE), Point::new(0, 0));
+        assert_eq!(h.anchor(Direction::SW), Point::new(3, -3));
+        for dir in Direction::iter() {
+            assert_eq!(h.frame_count(dir), 2);
+            let d = dir as i32;
+            assert_eq!(h.directions[dir].center, Point::new(d, -d));
+        }
+        assert_eq!(h.directions[Direction::NE].offset, 0);
+        assert_eq!(h.directions[Direction::E].offset, 2 * FrmHeader::FRAME_HEADER_LEN + 7);
+        assert_eq!(h.directions[Direction::NW].offset, h.directions[Direction::E].offset);
+
+        assert_eq!(h.frame_size(Direction::NE, 0), Some((2, 3)));
+        assert_eq!(h.frame_size(Direction::NE, 1), Some((1, 1)));
+        assert_eq!(h.frame_size(Direction::SW, 1), Some((2, 2)));
+        assert_eq!(h.frame_size(Direction::SW, 2), None);
+
+        assert_eq!(h.direction_bounds(Direction::NE), (2, 3));
+        assert_eq!(h.direction_bounds(Direction::E), (3, 2));
+
+        assert_eq!(h.frame(Direction::NE, 1).unwrap(), &FrmFrameInfo {
+            width: 1,
+            height: 1,
+            shift: Point::new(1, -1),
+            pixels_offset: 2 * FrmHeader::FRAME_HEADER_LEN + 6,
+        });
+        assert_eq!(h.frame(Direction::W, 1).unwrap().pixels_offset,
+            4 * FrmHeader::FRAME_HEADER_LEN + 7 + 3);
+    }
+
+    #[test]
+    fn decode_frame() {
+        let frm = Frm::read(&mut &sample_frm()[..]).unwrap();
+
+        assert_eq!(frm.decode_frame(Direction::NE, 1).unwrap(), FrmFrame {
+            width: 1,
+            height: 1,
+            shift: Point::new(1, -1),
+            pixels: vec![7].into(),
+        });
+        assert_eq!(frm.decode_frame(Direction::NW, 1).unwrap(), FrmFrame {
+            width: 2,
+            height: 2,
+            shift: Point::new(-2, 0),
+            pixels: vec![10, 11, 12, 13].into(),
+        });
+        assert_eq!(frm.decode_frame(Direction::E, 0).unwrap().pixels, vec![0, 8, 9].into());
+        assert_eq!(frm.decode_frame(Direction::E, 2), None);
+    }
+
+    #[test]
+    fn parse_frm_() {
+        let data = sample_frm();
+        let frm = parse_frm(&data).unwrap();
+        assert_eq!(frm.header().fps, 12);
+        assert_eq!(frm.header().frame_count(Direction::NE), 2);
+
+        let pixels = frm.frame_pixels(Direction::NW, 1).unwrap();
+        assert_eq!(pixels, &[10, 11, 12, 13]);
+        let data_range = data.as_ptr_range();
+        assert!(data_range.contains(&pixels.as_ptr()));
+        assert_eq!(frm.decode_frame(Direction::NW, 1).unwrap().pixels, pixels.into());
+        assert_eq!(frm.frame_pixels(Direction::E, 2), None);
+
+        assert!(parse_frm(&data[..10]).is_err());
+    }
+
+    #[test]
+    fn pack_atlas() {
+        use crate::graphics::color::{Rgb, Rgb18};
+
+        let mut colors = [Rgb18::black(); 256];
+        for (i, c) in colors.iter_mut().enumerate() {
+            *c = Rgb::new(i as u8 % 64, 0, 0);
+        }
+        let palette = Palette::new(colors, [0; 32768], [true; 256]);
+        let frm = Frm::read(&mut &sample_frm()[..]).unwrap();
+        let (pixels, width, height, rects) = frm.pack_atlas(&palette);
+
+        assert_eq!(pixels.len(), width as usize * height as usize * 4);
+        assert_eq!(rects.len(), 12);
+        let atlas = Rect::with_size(0, 0, width as i32, height as i32);
+        for (i, a) in rects.iter().enumerate() {
+            assert_eq!(a.intersect(atlas), *a);
+            for b in &rects[i + 1..] {
+                assert!(a == b || !a.intersects(*b));
+            }
+        }
+        // Directions E..NW share the frames.
+        assert_eq!(rects[2], rects[10]);
+        assert_ne!(rects[0], rects[2]);
+
+        for (i, dir) in Direction::iter().enumerate() {
+            for (j, frame) in frm.decode_all_frames(dir).into_iter().enumerate() {
+                let r = rects[i * 2 + j];
+                assert_eq!((r.width(), r.height()), (frame.width as i32, frame.height as i32));
+                for y in 0..r.height() {
+                    for x in 0..r.width() {
+                        let color_idx = frame.pixels[(y * r.width() + x) as usize];
+                        let p = (((r.top + y) * width as i32 + r.left + x) * 4) as usize;
+                        let exp = if color_idx == 0 {
+                            [0; 4]
+                        } else {
+                            let c = palette.rgb24(color_idx);
+                            [c.r(), c.g(), c.b(), 0xff]
+                        };
+                        assert_eq!(pixels[p..p + 4], exp);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn accumulated_offset() {
+        let frm = make_frm([0; 6], &[
+            vec![
+                (1, 1, (1, 0), vec![1]),
+                (1, 1, (2, -1), vec![2]),
+                (1, 1, (3, 1), vec![3]),
+            ],
+        ]);
+        let h = FrmHeader::read(&mut &frm[..]).unwrap();
+        assert_eq!(h.accumulated_offset(Direction::SE, 0), Point::new(1, 0));
+        assert_eq!(h.accumulated_offset(Direction::SE, 1), Point::new(3, -1));
+        assert_eq!(h.accumulated_offset(Direction::SE, 2), Point::new(6, 0));
+        assert_eq!(h.accumulated_offset(Direction::SE, 100), Point::new(6, 0));
+    }
+
+    #[test]
+    fn resolve_direction() {
+        let h = FrmHeader::read(&mut &sample_frm()[..]).unwrap();
+        for dir in Direction::iter() {
+            assert_eq!(h.resolve_direction(dir), (dir, false));
+        }
+
+        let frm = make_frm([0, 1, 2, 3, 3, 3], &[
+            vec![(1, 1, (0, 0), vec![1])],
+            vec![(1, 1, (0, 0), vec![2])],
+            vec![(1, 1, (0, 0), vec![3])],
+            vec![(0, 0, (0, 0), vec![])],
+        ]);
+        let h = FrmHeader::read(&mut &frm[..]).unwrap();
+        use Direction::*;
+        for &(dir, exp) in &[
+            (NE, (NE, false)),
+            (E, (E, false)),
+            (SE, (SE, false)),
+            (SW, (SE, true)),
+            (W, (E, true)),
+            (NW, (NE, true)),
+        ] {
+            assert_eq!(h.resolve_direction(dir), exp, "{:?}", dir);
+        }
+    }
+
+    #[test]
+    fn decode_all_frames() {
+        let frm = Frm::read(&mut &sample_frm()[..]).unwrap();
+        for dir in Direction::iter() {
+            let frames = frm.decode_all_frames(dir);
+            assert_eq!(frames.len(), 2);
+            for (i, f) in frames.into_iter().enumerate() {
+                assert_eq!(Some(f), frm.decode_frame(dir, i));
+            }
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn decode_all_frames_parallel() {
+        let frames: Vec<_> = (0..100)
+            .map(|i| (i % 7 + 1, i % 3 + 1, (i as i16, -(i as i16)),
+                vec![i as u8; (i % 7 + 1) as usize * (i % 3 + 1) as usize]))
+            .collect();
+        let reversed = frames.iter().cloned().rev().collect();
+        let data = make_frm([0, 0, 0, 1, 1, 1], &[frames, reversed]);
+        let frm = Frm::read(&mut &data[..]).unwrap();
+        for dir in Direction::iter() {
+            let serial: Vec<_> = (0..frm.header().frame_count(dir))
+                .map(|i| frm.decode_frame(dir, i).unwrap())
+                .collect();
+            assert_eq!(frm.decode_all_frames(dir), serial);
+        }
+        assert_eq!(frm.decode_all_frames(Direction::NE).len(), 100);
+    }
+
+    #[test]
+    fn read_header_truncated() {
+        let frm = sample_frm();
+        assert!(FrmHeader::read(&mut &frm[..frm.len() - 1]).is_err());
+        assert!(FrmHeader::read(&mut &frm[..10]).is_err());
+    }
+}
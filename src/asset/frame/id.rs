@@ -396,6 +396,19 @@ impl Into<FrameId> for Generic {
     }
 }
 
+/// Returns frame ID of the critter `anim` animation with the `weapon` art facing the `direction`
+/// for the critter whose base art is `base`. Direction `None` selects the art containing all
+/// directions. Returns `None` if `base` is not a critter frame ID.
+pub fn critter_anim_fid(base: FrameId, anim: CritterAnim, weapon: WeaponKind,
+    direction: Option<Direction>) -> Option<FrameId>
+{
+    Some(base.critter()?
+        .with_anim(anim)
+        .with_weapon(weapon)
+        .with_direction(direction)
+        .into())
+}
+
 struct Parts {
     kind: EntityKind,
     direction: Option<Direction>,
@@ -441,3 +454,65 @@ fn unpack(fid: u32) -> Option<Parts> {
     })
 }
 
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let fid = FrameId::new_critter(Some(Direction::SW), CritterAnim::Walk, WeaponKind::Rifle,
+            0x123).unwrap();
+        assert_eq!(fid.kind(), EntityKind::Critter);
+        assert_eq!(fid.direction(), Some(Direction::SW));
+        let critter = fid.critter().unwrap();
+        assert_eq!(critter.anim(), CritterAnim::Walk);
+        assert_eq!(critter.weapon(), WeaponKind::Rifle);
+        assert_eq!(fid.id(), 0x123);
+        assert_eq!(FrameId::new(fid.kind(), fid.direction(), fid.anim(), fid.sub_anim(), fid.id()),
+            Some(fid));
+        assert_eq!(FrameId::from_packed(fid.packed()), Some(fid));
+
+        let fid = FrameId::new_generic(EntityKind::Scenery, 0xfff).unwrap();
+        assert_eq!(fid.kind(), EntityKind::Scenery);
+        assert_eq!(fid.direction(), None);
+        assert_eq!(fid.id(), 0xfff);
+        assert_eq!(FrameId::new(fid.kind(), None, 0, 0, fid.id()), Some(fid));
+
+        assert_eq!(FrameId::new_generic(EntityKind::Scenery, 0x1000), None);
+        assert_eq!(FrameId::new(EntityKind::Item, None, 0, 16, 0), None);
+    }
+
+    #[test]
+    fn critter_anim_fid_() {
+        let base = FrameId::new_critter(None, CritterAnim::Stand, WeaponKind::Unarmed, 0x42)
+            .unwrap();
+
+        let walk = critter_anim_fid(base, CritterAnim::Walk, WeaponKind::Unarmed, Some(Direction::E))
+            .unwrap();
+        assert_eq!(walk.packed(), (Direction::E as u32 + 1) << 28 | 1 << 24
+            | (CritterAnim::Walk as u32) << 16 | 0x42);
+        assert_eq!(walk.direction(), Some(Direction::E));
+        assert_eq!(walk.critter().unwrap().anim(), CritterAnim::Walk);
+        assert_eq!(walk.critter().unwrap().weapon(), WeaponKind::Unarmed);
+        assert_eq!(walk.id(), 0x42);
+
+        let attack = critter_anim_fid(walk, CritterAnim::FireSingle, WeaponKind::Rifle,
+            Some(Direction::SW)).unwrap();
+        let critter = attack.critter().unwrap();
+        assert_eq!(critter.direction(), Some(Direction::SW));
+        assert_eq!(critter.anim(), CritterAnim::FireSingle);
+        assert_eq!(critter.weapon(), WeaponKind::Rifle);
+        assert_eq!(critter.id(), 0x42);
+        assert_eq!(attack.sub_anim(), WeaponKind::Rifle as u8);
+
+        let stand = critter_anim_fid(attack, CritterAnim::Stand, WeaponKind::Unarmed, None)
+            .unwrap();
+        assert_eq!(stand.direction(), None);
+        assert_eq!(stand.critter().unwrap().anim(), CritterAnim::Stand);
+        assert_eq!(stand.packed(), base.packed());
+
+        let scenery = FrameId::new_generic(EntityKind::Scenery, 1).unwrap();
+        assert_eq!(critter_anim_fid(scenery, CritterAnim::Walk, WeaponKind::Unarmed,
+            Some(Direction::NE)), None);
+    }
+}
@@ -0,0 +1,127 @@
+use byteorder::{BigEndian, WriteBytesExt};
+use flate2::Compression;
+use flate2::Crc;
+use flate2::write::ZlibEncoder;
+use std::io::{self, prelude::*};
+
+use crate::graphics::color::palette::Palette;
+use super::FrmFrame;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+const BIT_DEPTH: u8 = 8;
+const COLOR_TYPE_RGBA: u8 = 6;
+const FILTER_NONE: u8 = 0;
+
+/// Writes `frame` as RGBA8 PNG image mapping the color indices through the `palette`.
+/// Color index 0 is written as fully transparent pixel.
+pub fn frm_frame_to_png(frame: &FrmFrame, palette: &Palette, mut writer: impl Write)
+    -> io::Result<()>
+{
+    writer.write_all(&SIGNATURE)?;
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.write_u32::<BigEndian>(frame.width as u32)?;
+    ihdr.write_u32::<BigEndian>(frame.height as u32)?;
+    ihdr.extend_from_slice(&[BIT_DEPTH, COLOR_TYPE_RGBA, 0, 0, 0]);
+    write_chunk(&mut writer, b"IHDR", &ihdr)?;
+
+    let mut enc = ZlibEncoder::new(Vec::new(), Compression::default());
+    if frame.width > 0 {
+        for row in frame.pixels.chunks(frame.width as usize) {
+            enc.write_all(&[FILTER_NONE])?;
+            for &color_idx in row {
+                let rgb = palette.rgb24(color_idx);
+                let alpha = if color_idx == 0 { 0 } else { 0xff };
+                enc.write_all(&[rgb.r(), rgb.g(), rgb.b(), alpha])?;
+            }
+        }
+    }
+    write_chunk(&mut writer, b"IDAT", &enc.finish()?)?;
+
+    write_chunk(&mut writer, b"IEND", &[])
+}
+
+fn write_chunk(writer: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    writer.write_u32::<BigEndian>(data.len() as u32)?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    let mut crc = Crc::new();
+    crc.update(kind);
+    crc.update(data);
+    writer.write_u32::<BigEndian>(crc.sum())
+}
+
+#[cfg(test)]
+mod test {
+    use byteorder::ReadBytesExt;
+    use flate2::read::ZlibDecoder;
+
+    use super::*;
+    use crate::graphics::Point;
+    use crate::graphics::color::Rgb18;
+
+    #[test]
+    fn frm_frame_to_png_() {
+        let mut colors = [Rgb18::new(0, 0, 0); 256];
+        colors[0] = Rgb18::new(63, 63, 63);
+        colors[1] = Rgb18::new(63, 0, 0);
+        colors[2] = Rgb18::new(0, 32, 63);
+        let pal = Palette::new(colors, [0; 32768], [true; 256]);
+
+        let frame = FrmFrame {
+            width: 3,
+            height: 2,
+            shift: Point::new(0, 0),
+            pixels: vec![0, 1, 2, 2, 1, 0].into(),
+        };
+        let mut png = Vec::new();
+        frm_frame_to_png(&frame, &pal, &mut png).unwrap();
+
+        assert_eq!(&png[..8], &SIGNATURE);
+        let mut rd = &png[8..];
+        let mut chunks = Vec::new();
+        while !rd.is_empty() {
+            let len = rd.read_u32::<BigEndian>().unwrap() as usize;
+            let kind = rd[..4].to_vec();
+            let data = rd[4..4 + len].to_vec();
+            rd = &rd[4 + len..];
+            let mut crc = Crc::new();
+            crc.update(&kind);
+            crc.update(&data);
+            assert_eq!(rd.read_u32::<BigEndian>().unwrap(), crc.sum());
+            chunks.push((kind, data));
+        }
+        let kinds: Vec<_> = chunks.iter().map(|(k, _)| &k[..]).collect();
+        assert_eq!(kinds, vec![&b"IHDR"[..], b"IDAT", b"IEND"]);
+
+        let ihdr = &chunks[0].1;
+        assert_eq!(&ihdr[..8], &[0, 0, 0, 3, 0, 0, 0, 2]);
+        assert_eq!(&ihdr[8..], &[BIT_DEPTH, COLOR_TYPE_RGBA, 0, 0, 0]);
+
+        let mut data = Vec::new();
+        ZlibDecoder::new(&chunks[1].1[..]).read_to_end(&mut data).unwrap();
+        assert_eq!(data.len(), 2 * (1 + 3 * 4));
+        assert_eq!(&data[..13], &[
+            FILTER_NONE,
+            0xff, 0xff, 0xff, 0,
+            0xff, 0, 0, 0xff,
+            0, 130, 0xff, 0xff]);
+
+        // Map the decoded pixels back to color indices and compare with the source frame.
+        let mut pixels = Vec::new();
+        for row in data.chunks(1 + frame.width as usize * 4) {
+            assert_eq!(row[0], FILTER_NONE);
+            for rgba in row[1..].chunks(4) {
+                let color_idx = (0..=255u8)
+                    .find(|&i| {
+                        let rgb = pal.rgb24(i);
+                        let alpha = if i == 0 { 0 } else { 0xff };
+                        rgba == [rgb.r(), rgb.g(), rgb.b(), alpha]
+                    })
+                    .unwrap();
+                pixels.push(color_idx);
+            }
+        }
+        assert_eq!(&pixels[..], &frame.pixels[..]);
+    }
+}
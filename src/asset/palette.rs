@@ -22,4 +22,27 @@ pub fn read_palette(rd: &mut impl Read) -> io::Result<Palette> {
     rd.read_exact(&mut rgb15_to_color_idx[..])?;
 
     Ok(Palette::new(color_idx_to_rgb18, rgb15_to_color_idx, mapped_colors))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    use crate::graphics::color::Rgb24;
+
+    #[test]
+    fn read_palette_() {
+        let mut data = vec![0; 256 * 3 + 32768];
+        data[..6].copy_from_slice(&[63, 0, 32, 64, 1, 1]);
+        let pal = read_palette(&mut Cursor::new(&data)).unwrap();
+        assert_eq!(pal.rgb24(0), Rgb24::new(255, 0, 130));
+        // Out of range values are left black.
+        assert_eq!(pal.rgb24(1), Rgb24::black());
+
+        let truncated = &data[..256 * 3 + 100];
+        assert!(read_palette(&mut Cursor::new(truncated)).is_err());
+        let truncated = &data[..100];
+        assert!(read_palette(&mut Cursor::new(truncated)).is_err());
+    }
+}
@@ -5,16 +5,21 @@ use bstring::{bstr, BString};
 use enumflags2::BitFlags;
 use enum_map::EnumMap;
 use num_traits::cast::FromPrimitive;
+use rand::Rng;
+use std::cmp;
 use std::ops::RangeInclusive;
 
 pub use id::ProtoId;
 pub use db::ProtoDb;
+#[cfg(test)]
+pub(crate) use db::test::proto_db;
 
 use super::*;
 use crate::asset::EntityKind;
 use crate::asset::frame::FrameId;
 use crate::asset::message::MessageId;
-use crate::game::script::ScriptPid;
+use crate::game::rpg::{SKILL_MAX, SkillDef, StatDef};
+use crate::game::script::{ScriptId, ScriptPid};
 use crate::graphics::geometry::hex::TileGrid;
 use crate::util::{enum_iter, EnumIter};
 
@@ -26,6 +31,23 @@ pub const MSG_YOU_SEE_X: MessageId = 480;
 
 pub type ProtoRef = std::rc::Rc<std::cell::RefCell<Proto>>;
 
+/// Problem found by `ProtoDb::validate()`.
+#[derive(Debug)]
+pub enum ValidationError {
+    /// Proto `pid` can't be loaded.
+    Load {
+        pid: ProtoId,
+        error: io::Error,
+    },
+
+    /// The `field` of proto `pid` references proto `target` that can't be loaded.
+    DanglingRef {
+        pid: ProtoId,
+        field: &'static str,
+        target: ProtoId,
+    },
+}
+
 #[derive(Debug)]
 pub struct Proto {
     id: ProtoId,
@@ -57,6 +79,40 @@ impl Proto {
         self.description.as_ref().map(|s| s.as_ref())
     }
 
+    /// Returns FID to display the proto in the `context`. Falls back to the map FID if the proto
+    /// has no art specific to the context.
+    pub fn display_fid(&self, context: DisplayContext) -> FrameId {
+        match context {
+            DisplayContext::World => None,
+            DisplayContext::Inventory => self.sub.as_item().and_then(|i| i.inventory_fid),
+            DisplayContext::Portrait => self.sub.as_critter().and_then(|c| c.head_fid),
+        }.unwrap_or(self.fid)
+    }
+
+    /// Returns multi-line dump of all fields of the proto including the kind-specific ones.
+    /// Intended for debugging.
+    pub fn describe(&self) -> String {
+        format!("{:?} {:?}\n{:#?}", self.id, self.kind(), self)
+    }
+
+    pub fn has_script(&self) -> bool {
+        self.script.is_some()
+    }
+
+    /// Returns ID of the script attached to the proto.
+    pub fn script(&self) -> Option<ScriptId> {
+        self.script
+    }
+
+    /// Returns `(light_radius, light_intensity)` if the proto emits light.
+    pub fn light_source(&self) -> Option<(i32, i32)> {
+        if self.light_radius > 0 {
+            Some((self.light_radius, self.light_intensity))
+        } else {
+            None
+        }
+    }
+
     // proto_action_can_use()
     pub fn can_use(&self) -> bool {
         self.flags_ext.contains(FlagExt::CanUse) ||
@@ -80,6 +136,96 @@ impl Proto {
         self.flags_ext.contains(FlagExt::CanPickup) ||
             self.kind() == ExactEntityKind::Item(ItemKind::Container)
     }
+
+    /// Flat objects are drawn beneath all non-flat objects and don't occlude them.
+    pub fn is_flat(&self) -> bool {
+        self.flags.contains(Flag::Flat)
+    }
+
+    pub fn blocks_movement(&self) -> bool {
+        !self.flags.contains(Flag::NoBlock)
+    }
+
+    pub fn blocks_light(&self) -> bool {
+        !self.flags.contains(Flag::LightThru)
+    }
+
+    pub fn blocks_shots(&self) -> bool {
+        !self.flags.contains(Flag::ShootThru)
+    }
+
+    /// Returns `true` if the object is drawn translucent so the objects behind it can be seen.
+    /// `TransNone` explicitly disables translucency.
+    pub fn is_trans_see(&self) -> bool {
+        !self.flags.contains(Flag::TransNone) && self.flags.intersects(
+            Flag::TransEnergy | Flag::TransGlass | Flag::TransRed | Flag::TransSteam |
+            Flag::TransWall)
+    }
+
+    /// Returns weapon kind of the weapon proto. Critters holding the weapon use this
+    /// for their animations.
+    // item_w_anim_code()
+    pub fn weapon_kind(&self) -> Option<WeaponKind> {
+        Some(self.sub.as_item()?.sub.as_weapon()?.animation_code)
+    }
+
+    // item_w_is_2handed()
+    pub fn is_two_handed(&self) -> bool {
+        self.kind() == ExactEntityKind::Item(ItemKind::Weapon) &&
+            self.flags_ext.contains(FlagExt::TwoHanded)
+    }
+
+    /// Returns perks granted by wearing, wielding or getting addicted to the item.
+    pub fn perks(&self) -> Vec<Perk> {
+        let item = if let Some(v) = self.sub.as_item() {
+            v
+        } else {
+            return Vec::new();
+        };
+        let perk = match &item.sub {
+            SubItem::Armor(v) => v.granted_perk(),
+            SubItem::Drug(v) => v.addiction.granted_perk(),
+            SubItem::Weapon(v) => v.granted_perk(),
+            | SubItem::Container(_)
+            | SubItem::Ammo(_)
+            | SubItem::Misc(_)
+            | SubItem::Key(_)
+            => None,
+        };
+        perk.into_iter().collect()
+    }
+}
+
+/// Where the proto art is displayed. See `Proto::display_fid()`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DisplayContext {
+    /// On the map.
+    World,
+    Inventory,
+    /// Talking head in dialogs.
+    Portrait,
+}
+
+/// Protos are identified by their PID: protos with the same PID are equal and protos are ordered
+/// by PID, that is by entity kind and then by index within the kind.
+impl PartialEq for Proto {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Proto {}
+
+impl Ord for Proto {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+
+impl PartialOrd for Proto {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -122,6 +268,56 @@ pub struct Item {
     pub sub: SubItem,
 }
 
+impl Item {
+    /// Returns FID to display the item in inventory falling back to the `proto_fid`.
+    pub fn inventory_fid_or(&self, proto_fid: FrameId) -> FrameId {
+        self.inventory_fid.unwrap_or(proto_fid)
+    }
+
+    /// Whether multiple items of this proto are shown as a single stack in inventory.
+    /// Weapons hold ammo, containers hold items and armor is worn individually, so they're never
+    /// stacked.
+    pub fn is_stackable(&self) -> bool {
+        match self.sub {
+            SubItem::Armor(_) | SubItem::Container(_) | SubItem::Weapon(_) => false,
+            SubItem::Drug(_) | SubItem::Ammo(_) | SubItem::Misc(_) | SubItem::Key(_) => true,
+        }
+    }
+
+    /// Maximum number of items in a single inventory stack.
+    pub fn max_stack(&self) -> i32 {
+        if self.is_stackable() {
+            i32::MAX
+        } else {
+            1
+        }
+    }
+
+    /// Returns the price adjusted by `barter_modifier_pct` percent. Positive modifier is a penalty:
+    /// when `buying` the price is raised by the modifier, when selling the price is divided by
+    /// the same factor so buying and then selling back never gains money. Negative modifier
+    /// works the other way. The result is never negative.
+    pub fn effective_price(&self, barter_modifier_pct: i32, buying: bool) -> i32 {
+        let price = cmp::max(self.price, 0) as i64;
+        // The factor is at least 1%.
+        let factor = cmp::max(100 + barter_modifier_pct as i64, 1);
+        let r = if buying {
+            price * factor / 100
+        } else {
+            price * 100 / factor
+        };
+        cmp::min(r, i32::MAX as i64) as i32
+    }
+}
+
+/// Total weight of `(item, count)` stacks. The result is clamped to the `i32` range.
+pub fn total_weight<'a>(items: impl IntoIterator<Item=(&'a Item, i32)>) -> i32 {
+    let r: i64 = items.into_iter()
+        .map(|(item, count)| item.weight as i64 * count as i64)
+        .sum();
+    num_traits::clamp(r, i32::MIN as i64, i32::MAX as i64) as i32
+}
+
 #[derive(Debug, enum_as_inner::EnumAsInner)]
 pub enum SubItem {
     Armor(Armor),
@@ -158,12 +354,36 @@ pub struct Armor {
   pub female_fid: FrameId,
 }
 
+impl Armor {
+    /// Returns `(damage_threshold, damage_resistance)` for every damage kind.
+    pub fn defense(&self) -> EnumMap<DamageKind, (i32, i32)> {
+        EnumMap::from(|k| (self.damage_threshold[k], self.damage_resistance[k]))
+    }
+
+    /// Perk the wearer gets while wearing the armor.
+    pub fn granted_perk(&self) -> Option<Perk> {
+        self.perk
+    }
+}
+
 #[derive(Debug)]
 pub struct Container {
     pub capacity: i32,
     pub flags: BitFlags<ContainerFlag>,
 }
 
+impl Container {
+    pub fn can_pick_up(&self) -> bool {
+        !self.flags.contains(ContainerFlag::CannotPickUp)
+    }
+
+    /// Whether `MagicHandsGround` animation should be played when using the container instead
+    /// of `MagicHandsMiddle`.
+    pub fn uses_magic_hands_ground(&self) -> bool {
+        self.flags.contains(ContainerFlag::MagicHandsGround)
+    }
+}
+
 #[derive(Clone, Copy, Debug, EnumFlags, Eq, PartialEq)]
 #[repr(u32)]
 pub enum ContainerFlag {
@@ -177,6 +397,28 @@ pub enum DrugEffectModifier {
     Random(i32, i32),
 }
 
+impl DrugEffectModifier {
+    /// Returns range of the possible stat changes. Bounds of `Random` are normalized so
+    /// the range is never empty.
+    pub fn range(self) -> RangeInclusive<i32> {
+        match self {
+            Self::Fixed(v) => v..=v,
+            Self::Random(a, b) => cmp::min(a, b)..=cmp::max(a, b),
+        }
+    }
+
+    /// Rolls the stat change within `range()`.
+    pub fn resolve(self, rng: &mut impl Rng) -> i32 {
+        match self {
+            Self::Fixed(v) => v,
+            Self::Random(..) => {
+                let range = self.range();
+                rng.gen_range(*range.start(), *range.end() + 1)
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct DrugEffect {
     pub delay: u32,
@@ -191,18 +433,89 @@ pub struct DrugAddiction {
     pub delay: u32,
 }
 
+impl DrugAddiction {
+    /// Perk the user gets when addicted.
+    pub fn granted_perk(&self) -> Option<Perk> {
+        self.perk
+    }
+
+    /// Rolls for addiction and returns the addiction perk and its onset delay on success.
+    /// Drugs without addiction perk never cause addiction so `None` is returned for them without
+    /// rolling at all, i.e. `rng` is left untouched.
+    pub fn roll(&self, rng: &mut impl Rng) -> Option<(Perk, u32)> {
+        let perk = self.perk?;
+        if rng.gen_range(1, 101) <= self.chance {
+            Some((perk, self.delay))
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Drug {
     pub effects: Vec<DrugEffect>,
     pub addiction: DrugAddiction,
 }
 
+impl Drug {
+    /// Applies effect at `effect_index` to the `stats` and returns the applied stat change.
+    /// `random` is used to roll the `DrugEffectModifier::Random` modifiers and has the signature
+    /// of `util::random::random()`. Returns `None` if there's no such effect.
+    pub fn apply_effect(&self, effect_index: usize, stats: &mut EnumMap<Stat, i32>,
+            random: impl FnOnce(i32, i32) -> i32) -> Option<i32> {
+        let effect = self.effects.get(effect_index)?;
+        let amount = match effect.modifier {
+            DrugEffectModifier::Fixed(v) => v,
+            m @ DrugEffectModifier::Random(..) => {
+                let range = m.range();
+                random(*range.start(), *range.end())
+            }
+        };
+        stats[effect.stat] += amount;
+        Some(amount)
+    }
+
+    /// Reverts effect at `effect_index` that was applied by `apply_effect()` which returned
+    /// the `applied` amount. Returns `false` if there's no such effect.
+    pub fn remove_effect(&self, effect_index: usize, applied: i32,
+            stats: &mut EnumMap<Stat, i32>) -> bool {
+        if let Some(effect) = self.effects.get(effect_index) {
+            stats[effect.stat] -= applied;
+            true
+        } else {
+            false
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Dual<T> {
     pub primary: T,
     pub secondary: T,
 }
 
+impl<T> Dual<T> {
+    pub fn map<U>(self, f: impl Fn(T) -> U) -> Dual<U> {
+        Dual {
+            primary: f(self.primary),
+            secondary: f(self.secondary),
+        }
+    }
+
+    pub fn get(&self, secondary: bool) -> &T {
+        if secondary {
+            &self.secondary
+        } else {
+            &self.primary
+        }
+    }
+
+    pub fn as_tuple(&self) -> (&T, &T) {
+        (&self.primary, &self.secondary)
+    }
+}
+
 #[derive(Debug)]
 pub struct Weapon {
     pub attack_kind: Dual<AttackKind>,
@@ -225,6 +538,84 @@ pub struct Weapon {
     pub sound_id: u8,
 }
 
+impl Weapon {
+    /// Value of `range_modifier()` for targets beyond the weapon range.
+    pub const OUT_OF_RANGE: i32 = -1000;
+
+    /// Returns `(attack_kind, ap_cost, max_range)` of the primary or `secondary` attack mode.
+    pub fn attack(&self, secondary: bool) -> (AttackKind, i32, i32) {
+        (*self.attack_kind.get(secondary), *self.ap_cost.get(secondary),
+            *self.max_range.get(secondary))
+    }
+
+    /// Average damage of the primary or `secondary` attack mode divided by its AP cost.
+    /// Burst attacks deal damage of each bullet in the burst. Returns 0 if the AP cost is 0.
+    pub fn damage_per_ap(&self, secondary: bool) -> f64 {
+        let (attack_kind, ap_cost, _) = self.attack(secondary);
+        if ap_cost <= 0 {
+            return 0.0;
+        }
+        let mut damage = (*self.damage.start() + *self.damage.end()) as f64 / 2.0;
+        if attack_kind == AttackKind::FireBurst {
+            damage *= self.burst_bullet_count as f64;
+        }
+        damage / ap_cost as f64
+    }
+
+    /// Whether the weapon can be reloaded with `ammo` of `ammo_pid` proto. The caliber must match
+    /// and if the weapon has `ammo_proto_id` set, `ammo_pid` must be that proto. Weapons without
+    /// caliber and `ammo_proto_id` don't use ammo.
+    // item_w_can_reload()
+    pub fn accepts_ammo(&self, ammo_pid: ProtoId, ammo: &Ammo) -> bool {
+        self.caliber == ammo.caliber && match self.ammo_proto_id {
+            Some(pid) => pid == ammo_pid,
+            None => self.caliber != 0,
+        }
+    }
+
+    /// Returns the to-hit penalty for wielding the weapon with `strength` lower than
+    /// `min_strength`. The penalty is 20 per each missing strength point.
+    // determine_to_hit_func()
+    pub fn strength_penalty(&self, strength: i32) -> i32 {
+        cmp::max(self.min_strength - strength, 0) * 20
+    }
+
+    /// Returns the to-hit adjustment for attacking a target at `distance` hexes with the primary
+    /// or `secondary` attack mode. Ranged attacks lose 4 per hex of distance and return
+    /// `OUT_OF_RANGE` beyond the weapon range. Melee attacks are not affected by the distance.
+    // determine_to_hit_func()
+    pub fn range_modifier(&self, secondary: bool, distance: i32) -> i32 {
+        let (attack_kind, _, max_range) = self.attack(secondary);
+        if !attack_kind.is_ranged() {
+            0
+        } else if distance > max_range {
+            Self::OUT_OF_RANGE
+        } else {
+            -4 * cmp::max(distance, 0)
+        }
+    }
+
+    /// Perk the wielder gets while holding the weapon.
+    pub fn granted_perk(&self) -> Option<Perk> {
+        self.perk
+    }
+
+    /// Computes reloading of a magazine of `max_ammo` capacity holding `current_ammo` rounds
+    /// from the `available` rounds. Returns `(new_loaded, consumed)` where `new_loaded` is the
+    /// number of rounds in the magazine after reloading and `consumed` is the number of rounds
+    /// taken from the `available` ones.
+    // item_w_reload()
+    pub fn reload(current_ammo: i32, available: i32, max_ammo: i32) -> (i32, i32) {
+        let consumed = cmp::max(cmp::min(max_ammo - current_ammo, available), 0);
+        (current_ammo + consumed, consumed)
+    }
+
+    /// Returns proto of the projectile or `None` if the weapon doesn't have one.
+    pub fn projectile(&self, db: &ProtoDb) -> io::Result<Option<ProtoRef>> {
+        self.projectile_pid.map(|pid| db.proto(pid)).transpose()
+    }
+}
+
 #[derive(Debug)]
 pub struct Ammo {
     pub caliber: i32,
@@ -235,6 +626,11 @@ pub struct Ammo {
     pub damage_div: i32,
 }
 
+/// Returns ID of the caliber name message in `proto.msg`.
+pub fn caliber_message_id(caliber: i32) -> MessageId {
+    300 + caliber
+}
+
 #[derive(Debug)]
 pub struct MiscItem {
     pub ammo_proto_id: Option<ProtoId>,
@@ -254,6 +650,22 @@ pub enum BodyKind {
     Robotic = 2,
 }
 
+impl BodyKind {
+    /// Returns `None` if `v` is not a valid body kind.
+    pub fn from_raw(v: u32) -> Option<Self> {
+        Self::from_u32(v)
+    }
+
+    pub fn as_raw(self) -> u32 {
+        self as u32
+    }
+
+    /// Returns ID of the body kind name message in `proto.msg`.
+    pub fn message_id(self) -> MessageId {
+        400 + self as MessageId
+    }
+}
+
 #[derive(Debug)]
 pub struct Critter {
     pub flags: BitFlags<CritterFlag>,
@@ -270,6 +682,62 @@ pub struct Critter {
     pub team_id: i32,
 }
 
+impl Critter {
+    /// Returns `(kill_kind, experience)` awarded for killing the critter.
+    pub fn kill_reward(&self) -> (CritterKillKind, i32) {
+        (self.kill_kind, self.experience)
+    }
+
+    /// Skill level derived from the skill points and the stats of the critter.
+    /// Doesn't include modifiers that apply to the dude only (tagged skills, traits, perks etc).
+    // skill_level()
+    pub fn effective_skill(&self, skill: Skill) -> i32 {
+        let r = SkillDef::defaults()[skill].level(self.skills[skill], |stat| self.total_stat(stat));
+        cmp::min(r, SKILL_MAX)
+    }
+
+    /// Sum of the base and bonus `stat` clamped to the valid range of the stat.
+    /// Doesn't include modifiers that apply to the dude only.
+    pub fn total_stat(&self, stat: Stat) -> i32 {
+        let def = &StatDef::defaults()[stat];
+        num_traits::clamp(self.base_stats[stat] + self.bonus_stats[stat], def.min, def.max)
+    }
+
+    /// Maximum weight the critter can carry derived from its strength.
+    // stat_recalc_derived()
+    pub fn carry_weight_limit(&self) -> i32 {
+        let def = &StatDef::defaults()[Stat::CarryWeight];
+        let base = 25 * self.total_stat(Stat::Strength) + 25;
+        num_traits::clamp(base + self.bonus_stats[Stat::CarryWeight], def.min, def.max)
+    }
+
+    pub fn ai_packet(&self) -> AiPacketId {
+        AiPacketId(self.ai_packet)
+    }
+
+    pub fn team_id(&self) -> TeamId {
+        TeamId(self.team_id)
+    }
+}
+
+/// Index of the AI packet in `data/ai.txt` that controls the critter behavior in combat.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct AiPacketId(pub i32);
+
+/// Team the critter belongs to in combat. The dude is always in team 0.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct TeamId(pub i32);
+
+impl TeamId {
+    pub const DUDE: Self = TeamId(0);
+
+    /// Whether critters of this team attack critters of the `other` team. Members of the same
+    /// team are never hostile to each other.
+    pub fn is_hostile_to(self, other: TeamId) -> bool {
+        self != other
+    }
+}
+
 #[derive(Clone, Copy, Debug, EnumFlags, Eq, PartialEq)]
 #[repr(u32)]
 pub enum CritterFlag {
@@ -309,6 +777,49 @@ pub enum CritterKillKind {
   BigBadBoss = 0x12,
 }
 
+impl CritterKillKind {
+    /// Returns `None` if `v` is not a valid kill kind.
+    pub fn from_raw(v: u32) -> Option<Self> {
+        Self::from_u32(v)
+    }
+
+    pub fn as_raw(self) -> u32 {
+        self as u32
+    }
+
+    /// Returns ID of the kill kind name message in `proto.msg`.
+    pub fn message_id(self) -> MessageId {
+        1450 + self as MessageId
+    }
+
+    /// Returns English name of the kill kind as shown in kill stats.
+    /// Use `message_id()` for the localized name.
+    pub fn display_name(self) -> &'static str {
+        use CritterKillKind::*;
+        match self {
+            Man => "Men",
+            Woman => "Women",
+            Children => "Children",
+            SuperMutant => "Super Mutants",
+            Ghoul => "Ghouls",
+            Brahmin => "Brahmin",
+            Radscorpion => "Radscorpions",
+            Rat => "Rats",
+            Floater => "Floaters",
+            Centaur => "Centaurs",
+            Robot => "Robots",
+            Dog => "Dogs",
+            Manti => "Manti",
+            DeathClaw => "Deathclaws",
+            Plant => "Plants",
+            Gecko => "Geckos",
+            Alien => "Aliens",
+            GiantAnt => "Giant Ants",
+            BigBadBoss => "Big Bad Boss",
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Scenery {
     pub material: Material,
@@ -316,6 +827,24 @@ pub struct Scenery {
     pub sub: SubScenery,
 }
 
+impl Scenery {
+    /// Whether the scenery with proto `flags` blocks movement. Open doors never block.
+    pub fn blocks_movement(&self, flags: BitFlags<Flag>) -> bool {
+        match &self.sub {
+            SubScenery::Door(door) => !door.is_open(),
+            _ => !flags.contains(Flag::NoBlock),
+        }
+    }
+
+    /// Whether the scenery with proto `flags` blocks sight. Open doors never block.
+    pub fn blocks_sight(&self, flags: BitFlags<Flag>) -> bool {
+        match &self.sub {
+            SubScenery::Door(door) => !door.is_open(),
+            _ => !flags.contains(Flag::LightThru),
+        }
+    }
+}
+
 #[derive(Debug, enum_as_inner::EnumAsInner)]
 pub enum SubScenery {
     Door(Door),
@@ -347,11 +876,48 @@ pub struct Door {
     pub key_id: i32,
 }
 
+impl Door {
+    pub fn is_open(&self) -> bool {
+        self.flags.contains(DoorFlag::Open)
+    }
+
+    pub fn set_open(&mut self, open: bool) {
+        if open {
+            self.flags.insert(DoorFlag::Open);
+        } else {
+            self.flags.remove(DoorFlag::Open);
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.flags.toggle(DoorFlag::Open);
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.flags.contains(DoorFlag::Locked)
+    }
+
+    pub fn is_jammed(&self) -> bool {
+        self.flags.contains(DoorFlag::Jammed)
+    }
+
+    /// Whether the `key` fits this door. Negative key IDs mean there's no key and never match.
+    pub fn unlocks_with(&self, key: &Key) -> bool {
+        self.key_id >= 0 && self.key_id == key.id
+    }
+}
+
 #[derive(Debug)]
 pub struct Stairs {
     pub exit: Option<MapExit>,
 }
 
+impl Stairs {
+    pub fn pos(&self) -> Option<EPoint> {
+        self.exit.as_ref().map(|e| e.pos)
+    }
+}
+
 #[derive(Debug)]
 pub struct Elevator {
     pub kind: u32,
@@ -370,6 +936,12 @@ pub struct Ladder {
     pub exit: Option<MapExit>,
 }
 
+impl Ladder {
+    pub fn pos(&self) -> Option<EPoint> {
+        self.exit.as_ref().map(|e| e.pos)
+    }
+}
+
 #[derive(Debug)]
 pub struct Wall {
     pub material: Material,
@@ -380,6 +952,12 @@ pub struct SqrTile {
     pub material: Material,
 }
 
+impl SqrTile {
+    pub fn is_passable(&self) -> bool {
+        !self.material.properties().impassable
+    }
+}
+
 #[derive(Clone, Copy, Eq, Debug, PartialEq)]
 pub enum WorldMapKind {
     Town,
@@ -394,16 +972,29 @@ pub struct MapExit {
 }
 
 impl MapExit {
+    /// Bits of the packed `location` holding the linear tile number.
+    pub const TILE_MASK: u32 = 0x3ffffff;
+
+    /// Bits of the packed `location` holding the direction.
+    pub const DIRECTION_MASK: u32 = 0x1c000000;
+    pub const DIRECTION_SHIFT: u32 = 26;
+
+    /// Bits of the packed `location` holding the elevation.
+    pub const ELEVATION_MASK: u32 = 0xe0000000;
+    pub const ELEVATION_SHIFT: u32 = 29;
+
+    /// Decodes exit from the `map` and packed `location` (`elevation << 29 | direction << 26 | tile`).
     pub fn decode(map: i32, location: u32) -> Option<MapExit> {
         let map = if map != 0 {
             TargetMap::decode(map)?
         } else {
             TargetMap::CurrentMap
         };
-        let elevation = location & 0x3ffffff;
-        let pos = TileGrid::default().from_linear_inv((location & 0xE0000000) >> 29)
+        let elevation = (location & Self::ELEVATION_MASK) >> Self::ELEVATION_SHIFT;
+        let pos = TileGrid::default().from_linear_inv(location & Self::TILE_MASK)
             .elevated(elevation);
-        let direction = Direction::from_u32((location & 0x1C000000) >> 26)?;
+        let direction = Direction::from_u32(
+            (location & Self::DIRECTION_MASK) >> Self::DIRECTION_SHIFT)?;
         Some(MapExit {
             map,
             pos,
@@ -437,4 +1028,814 @@ impl TargetMap {
 // Subset that has prototypes.
 pub fn proto_entity_kinds() -> EnumIter<EntityKind> {
     enum_iter(..=EntityKind::Misc)
-}
\ No newline at end of file
+}
+
+pub fn item_kinds() -> EnumIter<ItemKind> {
+    enum_iter(..)
+}
+
+pub fn weapon_kinds() -> EnumIter<WeaponKind> {
+    enum_iter(..)
+}
+
+pub fn materials() -> EnumIter<Material> {
+    enum_iter(..)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+
+    fn proto(flags_ext: BitFlags<FlagExt>, sub: SubProto) -> Proto {
+        Proto {
+            id: ProtoId::new(EntityKind::Item, 1).unwrap(),
+            name: None,
+            description: None,
+            fid: FrameId::new(EntityKind::Item, None, 0, 0, 0).unwrap(),
+            light_radius: 0,
+            light_intensity: 0,
+            flags: BitFlags::empty(),
+            flags_ext,
+            script: None,
+            sub,
+        }
+    }
+
+    fn item(sub: SubItem) -> SubProto {
+        SubProto::Item(Item {
+            material: Material::Metal,
+            size: 1,
+            weight: 1,
+            price: 0,
+            inventory_fid: None,
+            sound_id: 0,
+            sub,
+        })
+    }
+
+    /// Returns single shot weapon with primary attack range 10 and AP cost 5.
+    pub fn weapon(animation_code: WeaponKind) -> Weapon {
+        Weapon {
+            attack_kind: Dual { primary: AttackKind::FireSingle, secondary: AttackKind::Stand },
+            animation_code,
+            damage: 1..=10,
+            damage_kind: DamageKind::Melee,
+            max_range: Dual { primary: 10, secondary: 0 },
+            projectile_pid: None,
+            min_strength: 0,
+            ap_cost: Dual { primary: 5, secondary: 0 },
+            crit_failure_table: 0,
+            perk: None,
+            burst_bullet_count: 0,
+            caliber: 0,
+            ammo_proto_id: None,
+            max_ammo_count: 0,
+            sound_id: 0,
+        }
+    }
+
+    #[test]
+    fn light_source() {
+        let mut p = proto(BitFlags::empty(), SubProto::Misc);
+        p.light_intensity = 0x10000;
+        assert_eq!(p.light_source(), None);
+        p.light_radius = 8;
+        assert_eq!(p.light_source(), Some((8, 0x10000)));
+    }
+
+    #[test]
+    fn script() {
+        use crate::game::script::ScriptKind;
+
+        let mut p = proto(BitFlags::empty(), SubProto::Misc);
+        assert!(!p.has_script());
+        assert_eq!(p.script(), None);
+
+        p.script = ScriptPid::from_packed(0x03_000012);
+        assert!(p.has_script());
+        let script = p.script().unwrap();
+        assert_eq!(script.kind(), ScriptKind::Item);
+        assert_eq!(script.program_id().val(), 0x12);
+    }
+
+    #[test]
+    fn flag_predicates() {
+        let mut p = proto(BitFlags::empty(), SubProto::Misc);
+        assert!(!p.is_flat());
+        assert!(p.blocks_movement());
+        assert!(p.blocks_light());
+        assert!(p.blocks_shots());
+        assert!(!p.is_trans_see());
+
+        p.flags = Flag::Flat | Flag::NoBlock | Flag::LightThru | Flag::ShootThru;
+        assert!(p.is_flat());
+        assert!(!p.blocks_movement());
+        assert!(!p.blocks_light());
+        assert!(!p.blocks_shots());
+
+        p.flags = Flag::TransGlass.into();
+        assert!(p.is_trans_see());
+
+        p.flags = Flag::TransGlass | Flag::TransNone;
+        assert!(!p.is_trans_see());
+    }
+
+    #[test]
+    fn proto_ord() {
+        let pid = |kind, id| ProtoId::new(kind, id).unwrap();
+        let with_id = |id| {
+            let mut r = proto(BitFlags::empty(), SubProto::Misc);
+            r.id = id;
+            r
+        };
+        let mut v = [
+            with_id(pid(EntityKind::Misc, 1)),
+            with_id(pid(EntityKind::Item, 7)),
+            with_id(pid(EntityKind::Critter, 2)),
+            with_id(pid(EntityKind::Item, 3)),
+        ];
+        v.sort();
+        assert_eq!(v.iter().map(|p| p.id()).collect::<Vec<_>>(), vec![
+            pid(EntityKind::Item, 3),
+            pid(EntityKind::Item, 7),
+            pid(EntityKind::Critter, 2),
+            pid(EntityKind::Misc, 1),
+        ]);
+
+        let mut p = with_id(pid(EntityKind::Item, 3));
+        p.light_radius = 8;
+        assert_eq!(p, v[0]);
+        assert_ne!(p, v[1]);
+    }
+
+    #[test]
+    fn action_affordances() {
+        let key = proto(FlagExt::CanPickup.into(), item(SubItem::Key(Key { id: 1 })));
+        assert!(key.can_pick_up());
+        assert!(!key.can_use());
+        assert!(!key.can_talk_to());
+
+        let mut box_ = proto(BitFlags::empty(),
+            item(SubItem::Container(Container { capacity: 10, flags: BitFlags::empty() })));
+        assert!(box_.can_pick_up());
+        assert!(box_.can_use());
+        box_.flags_ext = FlagExt::CanUseOn.into();
+        assert!(box_.can_use_on());
+
+        let scenery = || SubProto::Scenery(Scenery {
+            material: Material::Stone,
+            sound_id: 0,
+            sub: SubScenery::Misc,
+        });
+        let rock = proto(BitFlags::empty(), scenery());
+        assert!(!rock.can_pick_up());
+        assert!(!rock.can_use());
+        assert!(!rock.can_talk_to());
+        let lever = proto(FlagExt::CanUse.into(), scenery());
+        assert!(!lever.can_pick_up());
+        assert!(lever.can_use());
+
+        let npc = proto(BitFlags::empty(), SubProto::Critter(critter()));
+        assert!(npc.can_talk_to());
+        assert!(!npc.can_pick_up());
+    }
+
+    #[test]
+    fn container_flags() {
+        let c = |flags| Container { capacity: 10, flags };
+
+        let v = c(BitFlags::empty());
+        assert!(v.can_pick_up());
+        assert!(!v.uses_magic_hands_ground());
+
+        let v = c(ContainerFlag::CannotPickUp.into());
+        assert!(!v.can_pick_up());
+        assert!(!v.uses_magic_hands_ground());
+
+        let v = c(ContainerFlag::MagicHandsGround.into());
+        assert!(v.can_pick_up());
+        assert!(v.uses_magic_hands_ground());
+
+        let v = c(ContainerFlag::CannotPickUp | ContainerFlag::MagicHandsGround);
+        assert!(!v.can_pick_up());
+        assert!(v.uses_magic_hands_ground());
+    }
+
+    #[test]
+    fn total_weight_() {
+        let item = |weight| {
+            let mut r = item(SubItem::Key(Key { id: 1 })).into_item().unwrap();
+            r.weight = weight;
+            r
+        };
+        assert_eq!(total_weight(vec![]), 0);
+
+        let heavy = item(1_000_000);
+        assert_eq!(total_weight(vec![(&heavy, 3)]), 3_000_000);
+        assert_eq!(total_weight(vec![(&heavy, 10_000)]), i32::MAX);
+
+        let light = item(2);
+        let medium = item(15);
+        assert_eq!(total_weight(vec![(&light, 5), (&medium, 1), (&heavy, 0)]), 25);
+    }
+
+    #[test]
+    fn display_fid() {
+        let inv_fid = FrameId::new(EntityKind::Inventory, None, 0, 0, 2).unwrap();
+        let mut p = proto(BitFlags::empty(), item(SubItem::Key(Key { id: 1 })));
+        assert_eq!(p.display_fid(DisplayContext::Inventory), p.fid);
+        p.sub.as_item_mut().unwrap().inventory_fid = Some(inv_fid);
+        assert_eq!(p.display_fid(DisplayContext::Inventory), inv_fid);
+        assert_eq!(p.display_fid(DisplayContext::World), p.fid);
+        assert_eq!(p.display_fid(DisplayContext::Portrait), p.fid);
+
+        let head_fid = FrameId::new_head(0, 0, 3).unwrap();
+        let mut p = proto(BitFlags::empty(), SubProto::Critter(critter()));
+        assert_eq!(p.display_fid(DisplayContext::Portrait), p.fid);
+        p.sub.as_critter_mut().unwrap().head_fid = Some(head_fid);
+        assert_eq!(p.display_fid(DisplayContext::Portrait), head_fid);
+        assert_eq!(p.display_fid(DisplayContext::World), p.fid);
+        assert_eq!(p.display_fid(DisplayContext::Inventory), p.fid);
+    }
+
+    #[test]
+    fn inventory_fid_or() {
+        let proto_fid = FrameId::new(EntityKind::Item, None, 0, 0, 1).unwrap();
+        let inv_fid = FrameId::new(EntityKind::Inventory, None, 0, 0, 2).unwrap();
+
+        let mut item = item(SubItem::Key(Key { id: 1 })).into_item().unwrap();
+        assert_eq!(item.inventory_fid_or(proto_fid), proto_fid);
+
+        item.inventory_fid = Some(inv_fid);
+        assert_eq!(item.inventory_fid_or(proto_fid), inv_fid);
+    }
+
+    fn critter() -> Critter {
+        Critter {
+            flags: BitFlags::empty(),
+            base_stats: EnumMap::new(),
+            bonus_stats: EnumMap::new(),
+            skills: EnumMap::new(),
+            body_kind: BodyKind::Biped,
+            experience: 0,
+            kill_kind: CritterKillKind::Man,
+            damage_kind: DamageKind::Melee,
+            head_fid: None,
+            ai_packet: 0,
+            team_id: 0,
+        }
+    }
+
+    #[test]
+    fn critter_team() {
+        let mut c = critter();
+        c.ai_packet = 12;
+        c.team_id = 3;
+        assert_eq!(c.ai_packet(), AiPacketId(12));
+        assert_eq!(c.team_id(), TeamId(3));
+
+        assert!(!c.team_id().is_hostile_to(TeamId(3)));
+        assert!(c.team_id().is_hostile_to(TeamId::DUDE));
+        assert!(TeamId::DUDE.is_hostile_to(c.team_id()));
+        assert!(!TeamId::DUDE.is_hostile_to(TeamId::DUDE));
+    }
+
+    #[test]
+    fn effective_skill() {
+        let mut c = critter();
+        c.base_stats[Stat::Agility] = 5;
+        c.bonus_stats[Stat::Agility] = 1;
+        c.base_stats[Stat::Perception] = 7;
+        c.base_stats[Stat::Intelligence] = 8;
+        c.skills[Skill::SmallGuns] = 10;
+
+        // 5 + 4 * AG
+        assert_eq!(c.effective_skill(Skill::SmallGuns), 5 + 4 * 6 + 10);
+        // 5 + PE + IN
+        assert_eq!(c.effective_skill(Skill::Doctor), 5 + 7 + 8);
+
+        // Stats are clamped to the valid range.
+        c.bonus_stats[Stat::Agility] = 10;
+        assert_eq!(c.effective_skill(Skill::SmallGuns), 5 + 4 * 10 + 10);
+
+        c.skills[Skill::SmallGuns] = 1000;
+        assert_eq!(c.effective_skill(Skill::SmallGuns), SKILL_MAX);
+    }
+
+    #[test]
+    fn body_kind_raw() {
+        assert_eq!(BodyKind::from_raw(0), Some(BodyKind::Biped));
+        assert_eq!(BodyKind::from_raw(1), Some(BodyKind::Quadruped));
+        assert_eq!(BodyKind::from_raw(2), Some(BodyKind::Robotic));
+        assert_eq!(BodyKind::from_raw(3), None);
+        assert_eq!(BodyKind::Robotic.as_raw(), 2);
+    }
+
+    #[test]
+    fn critter_kill_kind_raw() {
+        assert_eq!(CritterKillKind::from_raw(0xf), Some(CritterKillKind::Gecko));
+        assert_eq!(CritterKillKind::from_raw(0x12), Some(CritterKillKind::BigBadBoss));
+        assert_eq!(CritterKillKind::from_raw(0x13), None);
+        assert_eq!(CritterKillKind::BigBadBoss.as_raw(), 0x12);
+    }
+
+    #[test]
+    fn dual() {
+        let d = Dual { primary: 5, secondary: 0 };
+        assert_eq!(*d.get(false), 5);
+        assert_eq!(*d.get(true), 0);
+        assert_eq!(d.as_tuple(), (&5, &0));
+
+        let d = d.map(|v| v.to_string());
+        assert_eq!(d.primary, "5");
+        assert_eq!(d.secondary, "0");
+    }
+
+    #[test]
+    fn accepts_ammo() {
+        let ammo = |caliber| Ammo {
+            caliber,
+            max_ammo_count: 24,
+            ac_modifier: 0,
+            dr_modifier: 0,
+            damage_mult: 1,
+            damage_div: 1,
+        };
+        let pid = |id| ProtoId::new(EntityKind::Item, id).unwrap();
+
+        let mut w = weapon(WeaponKind::Pistol);
+        w.caliber = 3;
+        assert!(w.accepts_ammo(pid(1), &ammo(3)));
+        assert!(w.accepts_ammo(pid(2), &ammo(3)));
+        assert!(!w.accepts_ammo(pid(1), &ammo(4)));
+
+        // Default ammo restricts the accepted ammo protos.
+        w.ammo_proto_id = Some(pid(1));
+        assert!(w.accepts_ammo(pid(1), &ammo(3)));
+        assert!(!w.accepts_ammo(pid(2), &ammo(3)));
+        assert!(!w.accepts_ammo(pid(1), &ammo(4)));
+
+        let mut melee = weapon(WeaponKind::Knife);
+        assert!(!melee.accepts_ammo(pid(1), &ammo(0)));
+        assert!(!melee.accepts_ammo(pid(1), &ammo(3)));
+
+        // Caliber 0 weapon accepts only its default caliber 0 ammo.
+        melee.ammo_proto_id = Some(pid(1));
+        assert!(melee.accepts_ammo(pid(1), &ammo(0)));
+        assert!(!melee.accepts_ammo(pid(2), &ammo(0)));
+        assert!(!melee.accepts_ammo(pid(1), &ammo(3)));
+    }
+
+    #[test]
+    fn damage_per_ap() {
+        let mut w = weapon(WeaponKind::Pistol);
+        w.damage = 10..=20;
+        w.ap_cost = Dual { primary: 5, secondary: 0 };
+        assert_eq!(w.damage_per_ap(false), 3.0);
+        assert_eq!(w.damage_per_ap(true), 0.0);
+
+        w.attack_kind.secondary = AttackKind::FireBurst;
+        w.ap_cost.secondary = 6;
+        w.burst_bullet_count = 10;
+        assert_eq!(w.damage_per_ap(true), 25.0);
+    }
+
+    #[test]
+    fn weapon_attack() {
+        let w = weapon(WeaponKind::Pistol);
+        assert_eq!(w.attack(false), (AttackKind::FireSingle, 5, 10));
+        assert_eq!(w.attack(true), (AttackKind::Stand, 0, 0));
+    }
+
+    #[test]
+    fn is_two_handed() {
+        let pistol = proto(BitFlags::empty(), item(SubItem::Weapon(weapon(WeaponKind::Pistol))));
+        assert!(!pistol.is_two_handed());
+
+        let rifle = proto(FlagExt::TwoHanded.into(),
+            item(SubItem::Weapon(weapon(WeaponKind::Rifle))));
+        assert!(rifle.is_two_handed());
+
+        let not_weapon = proto(FlagExt::TwoHanded.into(), SubProto::Misc);
+        assert!(!not_weapon.is_two_handed());
+    }
+
+    #[test]
+    fn drug_addiction_roll() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let addiction = |chance, perk| DrugAddiction {
+            chance,
+            perk,
+            delay: 120,
+        };
+        let mut rng = StdRng::seed_from_u64(42);
+
+        for _ in 0..100 {
+            assert_eq!(addiction(0, Some(Perk::AddJet)).roll(&mut rng), None);
+            assert_eq!(addiction(100, Some(Perk::AddJet)).roll(&mut rng),
+                Some((Perk::AddJet, 120)));
+            assert_eq!(addiction(100, None).roll(&mut rng), None);
+        }
+
+        let roll_50 = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..100)
+                .filter(|_| addiction(50, Some(Perk::AddJet)).roll(&mut rng).is_some())
+                .count()
+        };
+        let hits = roll_50(42);
+        assert_eq!(roll_50(42), hits);
+        assert!(hits > 20 && hits < 80);
+    }
+
+    #[test]
+    fn drug_effect_modifier() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        assert_eq!(DrugEffectModifier::Fixed(-2).range(), -2..=-2);
+        assert_eq!(DrugEffectModifier::Random(-3, 1).range(), -3..=1);
+        assert_eq!(DrugEffectModifier::Random(5, 2).range(), 2..=5);
+
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..100 {
+            assert_eq!(DrugEffectModifier::Fixed(-2).resolve(&mut rng), -2);
+            assert!((-3..=1).contains(&DrugEffectModifier::Random(-3, 1).resolve(&mut rng)));
+            assert!((2..=5).contains(&DrugEffectModifier::Random(5, 2).resolve(&mut rng)));
+        }
+    }
+
+    #[test]
+    fn drug_effects() {
+        let drug = Drug {
+            effects: vec![
+                DrugEffect {
+                    delay: 0,
+                    stat: Stat::Strength,
+                    modifier: DrugEffectModifier::Fixed(2),
+                },
+                DrugEffect {
+                    delay: 60,
+                    stat: Stat::Agility,
+                    modifier: DrugEffectModifier::Random(-3, 1),
+                },
+            ],
+            addiction: DrugAddiction {
+                chance: 0,
+                perk: None,
+                delay: 0,
+            },
+        };
+        let mut stats = EnumMap::new();
+        stats[Stat::Strength] = 5;
+        stats[Stat::Agility] = 6;
+        let orig_stats = stats;
+
+        let applied = drug.apply_effect(0, &mut stats, |_, _| unreachable!()).unwrap();
+        assert_eq!(applied, 2);
+        assert_eq!(stats[Stat::Strength], 7);
+        assert_eq!(stats[Stat::Agility], 6);
+        assert!(drug.remove_effect(0, applied, &mut stats));
+        assert_eq!(stats, orig_stats);
+
+        let applied = drug.apply_effect(1, &mut stats, |from, to| {
+            assert_eq!((from, to), (-3, 1));
+            -2
+        }).unwrap();
+        assert_eq!(applied, -2);
+        assert_eq!(stats[Stat::Agility], 4);
+        assert!(drug.remove_effect(1, applied, &mut stats));
+        assert_eq!(stats, orig_stats);
+
+        assert_eq!(drug.apply_effect(2, &mut stats, |_, _| 0), None);
+        assert!(!drug.remove_effect(2, 0, &mut stats));
+    }
+
+    #[test]
+    fn weapon_kind() {
+        let rifle = proto(FlagExt::TwoHanded.into(),
+            item(SubItem::Weapon(weapon(WeaponKind::Rifle))));
+        assert_eq!(rifle.weapon_kind(), Some(WeaponKind::Rifle));
+        let key = proto(BitFlags::empty(), item(SubItem::Key(Key { id: 1 })));
+        assert_eq!(key.weapon_kind(), None);
+
+        let critter_fid = FrameId::new_critter(None, CritterAnim::Stand, WeaponKind::Unarmed, 1)
+            .unwrap()
+            .critter()
+            .unwrap();
+        let walk_fid = |weapon: Option<&Proto>| critter_fid
+            .with_anim(CritterAnim::Walk)
+            .with_weapon(weapon.and_then(|p| p.weapon_kind()).unwrap_or(WeaponKind::Unarmed));
+        let armed = walk_fid(Some(&rifle));
+        let unarmed = walk_fid(None);
+        assert_ne!(armed, unarmed);
+        assert_eq!(armed.weapon(), WeaponKind::Rifle);
+        assert_eq!(unarmed.weapon(), WeaponKind::Unarmed);
+    }
+
+    #[test]
+    fn door_unlocks_with() {
+        let door = Door {
+            flags: DoorFlag::Locked.into(),
+            key_id: 42,
+        };
+        assert!(door.is_locked());
+        assert!(door.unlocks_with(&Key { id: 42 }));
+        assert!(!door.unlocks_with(&Key { id: 43 }));
+
+        let door = Door {
+            flags: BitFlags::empty(),
+            key_id: -1,
+        };
+        assert!(!door.is_locked());
+        assert!(!door.unlocks_with(&Key { id: -1 }));
+    }
+
+    #[test]
+    fn door_open() {
+        let mut door = Door {
+            flags: DoorFlag::Locked | DoorFlag::Jammed,
+            key_id: -1,
+        };
+        let orig_flags = door.flags;
+        assert!(!door.is_open());
+        assert!(door.is_locked());
+        assert!(door.is_jammed());
+
+        door.toggle();
+        assert!(door.is_open());
+        assert_eq!(door.flags, orig_flags | DoorFlag::Open);
+        door.toggle();
+        assert!(!door.is_open());
+        assert_eq!(door.flags, orig_flags);
+
+        door.set_open(true);
+        door.set_open(true);
+        assert!(door.is_open());
+        door.set_open(false);
+        assert_eq!(door.flags, orig_flags);
+    }
+
+    #[test]
+    fn scenery_blocking() {
+        let scenery = |sub| Scenery {
+            material: Material::Wood,
+            sound_id: 0,
+            sub,
+        };
+
+        let closed = scenery(SubScenery::Door(Door { flags: BitFlags::empty(), key_id: -1 }));
+        assert!(closed.blocks_movement(Flag::NoBlock | Flag::LightThru));
+        assert!(closed.blocks_sight(Flag::NoBlock | Flag::LightThru));
+
+        let open = scenery(SubScenery::Door(Door { flags: DoorFlag::Open.into(), key_id: -1 }));
+        assert!(!open.blocks_movement(BitFlags::empty()));
+        assert!(!open.blocks_sight(BitFlags::empty()));
+
+        let misc = scenery(SubScenery::Misc);
+        assert!(misc.blocks_movement(BitFlags::empty()));
+        assert!(misc.blocks_sight(BitFlags::empty()));
+        assert!(!misc.blocks_movement(Flag::NoBlock.into()));
+        assert!(misc.blocks_sight(Flag::NoBlock.into()));
+        assert!(!misc.blocks_sight(Flag::LightThru.into()));
+    }
+
+    #[test]
+    fn map_exit_decode() {
+        let tile_grid = TileGrid::default();
+
+        let exit = MapExit::decode(0, 1 << 29 | 2 << 26 | 20100).unwrap();
+        assert_eq!(exit.map, TargetMap::CurrentMap);
+        assert_eq!(exit.pos, tile_grid.from_linear_inv(20100).elevated(1));
+        assert_eq!(exit.direction, Direction::SE);
+
+        let exit = MapExit::decode(5, 2 << 29 | 5 << 26 | 12345).unwrap();
+        assert_eq!(exit.map, TargetMap::Map { map_id: 5 });
+        assert_eq!(exit.pos, tile_grid.from_linear_inv(12345).elevated(2));
+        assert_eq!(exit.direction, Direction::NW);
+
+        let stairs = Stairs { exit: Some(exit) };
+        assert_eq!(stairs.pos(), Some(tile_grid.from_linear_inv(12345).elevated(2)));
+
+        // Invalid direction.
+        assert!(MapExit::decode(0, 6 << 26).is_none());
+    }
+
+    fn armor(perk: Option<Perk>) -> Armor {
+        Armor {
+            armor_class: 5,
+            damage_resistance: EnumMap::from(|k| k as i32 % 50 + 1),
+            damage_threshold: EnumMap::from(|k| k as i32 % 50 + 100),
+            perk,
+            male_fid: FrameId::new(EntityKind::Critter, None, 0, 0, 1).unwrap(),
+            female_fid: FrameId::new(EntityKind::Critter, None, 0, 0, 2).unwrap(),
+        }
+    }
+
+    #[test]
+    fn armor_defense() {
+        let armor = armor(None);
+        let defense = armor.defense();
+        for (k, &(dt, dr)) in &defense {
+            assert_eq!(dt, armor.damage_threshold[k]);
+            assert_eq!(dr, armor.damage_resistance[k]);
+        }
+        assert_eq!(defense[DamageKind::Poison], (101, 2));
+        assert_eq!(defense.iter().count(), 9);
+    }
+
+    #[test]
+    fn perks() {
+        let a = armor(Some(Perk::ArmorPowered));
+        assert_eq!(a.granted_perk(), Some(Perk::ArmorPowered));
+        assert_eq!(proto(BitFlags::empty(), item(SubItem::Armor(a))).perks(),
+            vec![Perk::ArmorPowered]);
+
+        let mut w = weapon(WeaponKind::Rifle);
+        assert_eq!(w.granted_perk(), None);
+        assert!(proto(BitFlags::empty(), item(SubItem::Weapon(w))).perks().is_empty());
+        w = weapon(WeaponKind::Rifle);
+        w.perk = Some(Perk::WeaponLongRange);
+        assert_eq!(proto(BitFlags::empty(), item(SubItem::Weapon(w))).perks(),
+            vec![Perk::WeaponLongRange]);
+
+        let drug = Drug {
+            effects: Vec::new(),
+            addiction: DrugAddiction {
+                chance: 10,
+                perk: Some(Perk::AddJet),
+                delay: 0,
+            },
+        };
+        assert_eq!(drug.addiction.granted_perk(), Some(Perk::AddJet));
+        assert_eq!(proto(BitFlags::empty(), item(SubItem::Drug(drug))).perks(),
+            vec![Perk::AddJet]);
+
+        assert!(proto(BitFlags::empty(), SubProto::Critter(critter())).perks().is_empty());
+    }
+
+    #[test]
+    fn message_id() {
+        assert_eq!(BodyKind::Biped.message_id(), 400);
+        assert_eq!(BodyKind::Robotic.message_id(), 402);
+        assert_eq!(CritterKillKind::Man.message_id(), 1450);
+        assert_eq!(CritterKillKind::BigBadBoss.message_id(), 1468);
+        assert_eq!(caliber_message_id(1), 301);
+    }
+
+    #[test]
+    fn weapon_projectile() {
+        let db = db::test::proto_db();
+
+        let mut w = weapon(WeaponKind::Pistol);
+        w.projectile_pid = Some(ProtoId::new(EntityKind::Misc, 2).unwrap());
+        let p = w.projectile(&db).unwrap().unwrap();
+        assert_eq!(p.borrow().light_radius, 2);
+
+        w.projectile_pid = Some(ProtoId::new(EntityKind::Misc, 99).unwrap());
+        assert!(w.projectile(&db).is_err());
+
+        assert!(weapon(WeaponKind::Knife).projectile(&db).unwrap().is_none());
+    }
+
+    #[test]
+    fn weapon_strength_penalty() {
+        let mut w = weapon(WeaponKind::Rifle);
+        w.min_strength = 6;
+        assert_eq!(w.strength_penalty(6), 0);
+        assert_eq!(w.strength_penalty(10), 0);
+        assert_eq!(w.strength_penalty(5), 20);
+        assert_eq!(w.strength_penalty(1), 100);
+    }
+
+    #[test]
+    fn describe() {
+        let mut w = weapon(WeaponKind::Rifle);
+        w.max_ammo_count = 24;
+        let mut p = proto(BitFlags::empty(), item(SubItem::Weapon(w)));
+        p.name = Some("Hunting Rifle".into());
+        let s = p.describe();
+        assert!(s.starts_with("ProtoId(0x00000001) Item(Weapon)\n"), "{}", s);
+        for field in &["\"Hunting Rifle\"", "Weapon(", "max_ammo_count: 24", "FireSingle"] {
+            assert!(s.contains(field), "{} not in {}", field, s);
+        }
+    }
+
+    #[test]
+    fn weapon_range_modifier() {
+        let mut w = weapon(WeaponKind::Rifle);
+        assert_eq!(w.range_modifier(false, 0), 0);
+        assert_eq!(w.range_modifier(false, 1), -4);
+        assert_eq!(w.range_modifier(false, 9), -36);
+        assert_eq!(w.range_modifier(false, 10), -40);
+        assert_eq!(w.range_modifier(false, 11), Weapon::OUT_OF_RANGE);
+
+        w.attack_kind.secondary = AttackKind::Thrust;
+        assert_eq!(w.range_modifier(true, 0), 0);
+        assert_eq!(w.range_modifier(true, 5), 0);
+    }
+
+    #[test]
+    fn critter_kill_reward() {
+        let mut c = critter();
+        c.kill_kind = CritterKillKind::Radscorpion;
+        c.experience = 110;
+        assert_eq!(c.kill_reward(), (CritterKillKind::Radscorpion, 110));
+
+        assert_eq!(CritterKillKind::Man.display_name(), "Men");
+        assert_eq!(CritterKillKind::DeathClaw.display_name(), "Deathclaws");
+        assert_eq!(CritterKillKind::BigBadBoss.display_name(), "Big Bad Boss");
+    }
+
+    #[test]
+    fn weapon_reload() {
+        assert_eq!(Weapon::reload(0, 50, 12), (12, 12));
+        assert_eq!(Weapon::reload(5, 50, 12), (12, 7));
+        assert_eq!(Weapon::reload(5, 3, 12), (8, 3));
+        assert_eq!(Weapon::reload(12, 50, 12), (12, 0));
+        assert_eq!(Weapon::reload(0, 0, 12), (0, 0));
+    }
+
+    #[test]
+    fn sqr_tile_is_passable() {
+        assert!(SqrTile { material: Material::Dirt }.is_passable());
+        assert!(SqrTile { material: Material::Cement }.is_passable());
+    }
+
+    #[test]
+    fn carry_weight_limit() {
+        let mut c = critter();
+        for &(st, exp) in &[(1, 50), (5, 150), (10, 275)] {
+            c.base_stats[Stat::Strength] = st;
+            assert_eq!(c.carry_weight_limit(), exp);
+        }
+
+        // Strength is clamped to the valid range.
+        c.bonus_stats[Stat::Strength] = 5;
+        assert_eq!(c.carry_weight_limit(), 275);
+
+        c.bonus_stats[Stat::CarryWeight] = 50;
+        assert_eq!(c.carry_weight_limit(), 325);
+    }
+
+    #[test]
+    fn item_effective_price() {
+        let mut item = item(SubItem::Key(Key { id: 1 })).into_item().unwrap();
+        item.price = 100;
+
+        assert_eq!(item.effective_price(0, true), 100);
+        assert_eq!(item.effective_price(0, false), 100);
+        assert_eq!(item.effective_price(25, true), 125);
+        assert_eq!(item.effective_price(25, false), 80);
+        assert_eq!(item.effective_price(-50, true), 50);
+        assert_eq!(item.effective_price(-200, true), 1);
+        assert_eq!(item.effective_price(-200, false), 10000);
+
+        item.price = -10;
+        assert_eq!(item.effective_price(25, true), 0);
+        assert_eq!(item.effective_price(25, false), 0);
+    }
+
+    #[test]
+    fn item_stackable() {
+        let ammo = item(SubItem::Ammo(Ammo {
+            caliber: 1,
+            max_ammo_count: 24,
+            ac_modifier: 0,
+            dr_modifier: 0,
+            damage_mult: 1,
+            damage_div: 1,
+        })).into_item().unwrap();
+        assert!(ammo.is_stackable());
+        assert_eq!(ammo.max_stack(), i32::MAX);
+
+        let weapon = item(SubItem::Weapon(weapon(WeaponKind::Pistol))).into_item().unwrap();
+        assert!(!weapon.is_stackable());
+        assert_eq!(weapon.max_stack(), 1);
+
+        let container = item(SubItem::Container(Container {
+            capacity: 10,
+            flags: BitFlags::empty(),
+        })).into_item().unwrap();
+        assert!(!container.is_stackable());
+        assert_eq!(container.max_stack(), 1);
+    }
+
+    #[test]
+    fn kinds() {
+        let v: Vec<_> = item_kinds().collect();
+        assert_eq!(v.len(), 7);
+        assert_eq!(v[0], ItemKind::Armor);
+        assert_eq!(v[6], ItemKind::Key);
+
+        let v: Vec<_> = weapon_kinds().collect();
+        assert_eq!(v.len(), 11);
+        assert_eq!(v[0], WeaponKind::Unarmed);
+        assert_eq!(v[10], WeaponKind::Launcher);
+
+        let v: Vec<_> = materials().collect();
+        assert_eq!(v.len(), 8);
+        assert_eq!(v[0], Material::Glass);
+        assert_eq!(v[7], Material::Leather);
+    }
+}
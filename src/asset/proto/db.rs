@@ -1,9 +1,10 @@
 use bstring::bstr;
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use enum_map::EnumMap;
 use num_traits::FromPrimitive;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use std::cell::RefCell;
-use std::collections::hash_map::{self, HashMap};
 use std::convert::{TryFrom, TryInto};
 use std::io::{self, Error, ErrorKind, prelude::*};
 use std::rc::Rc;
@@ -14,23 +15,37 @@ use crate::asset::frame::*;
 use crate::asset::message::{MessageId, Messages};
 use crate::game::script::ScriptPid;
 use crate::fs::FileSystem;
+use crate::util::lru::LruCache;
 
 pub struct ProtoDb {
     fs: Rc<FileSystem>,
     lst: Lst,
     messages: Messages,
     entity_messages: EnumMap<EntityKind, Messages>,
-    protos: RefCell<HashMap<ProtoId, ProtoRef>>,
+    dude: ProtoRef,
+    protos: RefCell<LruCache<ProtoId, ProtoRef>>,
 }
 
 impl ProtoDb {
     pub fn new(fs: Rc<FileSystem>, language: &str) -> io::Result<Self> {
         let lst = Lst::read(&fs)?;
+        Self::with_lst(fs, language, lst)
+    }
+
+    /// Same as `new()` but reads the proto index from `rd` previously written by `save_index()`
+    /// instead of the `.lst` files.
+    pub fn load_index(fs: Rc<FileSystem>, language: &str, rd: &mut impl Read)
+        -> io::Result<Self>
+    {
+        let lst = Lst::read_index(rd)?;
+        Self::with_lst(fs, language, lst)
+    }
+
+    fn with_lst(fs: Rc<FileSystem>, language: &str, lst: Lst) -> io::Result<Self> {
         let messages = Messages::read_file(&fs, language, "game/proto.msg")?;
         let entity_messages = Self::read_entity_messages(&fs, language)?;
 
-        let mut protos = HashMap::new();
-        protos.insert(ProtoId::DUDE, Rc::new(RefCell::new(Proto {
+        let dude = Rc::new(RefCell::new(Proto {
             id: ProtoId::DUDE,
             name: None,
             description: None,
@@ -53,44 +68,136 @@ impl ProtoDb {
                 ai_packet: 0,
                 team_id: 0
             }),
-        })));
+        }));
 
         Ok(Self {
             fs,
             lst,
             messages,
             entity_messages,
-            protos: RefCell::new(protos),
+            dude,
+            protos: RefCell::new(LruCache::new(usize::MAX)),
         })
     }
 
+    /// Limits the number of parsed protos kept in memory to `capacity`. Least recently used
+    /// protos are evicted and parsed again on the next access. Note the evicted protos that are
+    /// still referenced elsewhere won't be shared with the newly parsed ones.
+    pub fn with_cache_capacity(self, capacity: usize) -> Self {
+        Self {
+            protos: RefCell::new(LruCache::new(capacity)),
+            ..self
+        }
+    }
+
     pub fn len(&self, kind: EntityKind) -> usize {
         self.lst.len(kind)
     }
 
+    /// Returns number of protos of each entity kind as listed in the index.
+    pub fn kind_counts(&self) -> EnumMap<EntityKind, usize> {
+        EnumMap::from(|k| self.len(k))
+    }
+
+    /// Loads every proto listed in the index and checks the protos referenced by it can be
+    /// loaded too. Note all the loaded protos stay in the cache.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut r = Vec::new();
+        for kind in proto_entity_kinds() {
+            for i in 0..self.len(kind) {
+                let pid = ProtoId::new(kind, i as u32 + 1).unwrap();
+                let proto = match self.proto(pid) {
+                    Ok(p) => p,
+                    Err(error) => {
+                        r.push(ValidationError::Load { pid, error });
+                        continue;
+                    }
+                };
+                let proto = proto.borrow();
+                let refs = match proto.sub.as_item().map(|item| &item.sub) {
+                    Some(SubItem::Weapon(w)) => vec![
+                        ("projectile_pid", w.projectile_pid),
+                        ("ammo_proto_id", w.ammo_proto_id),
+                    ],
+                    Some(SubItem::Misc(m)) => vec![("ammo_proto_id", m.ammo_proto_id)],
+                    _ => Vec::new(),
+                };
+                for (field, target) in refs {
+                    if let Some(target) = target {
+                        if self.proto(target).is_err() {
+                            r.push(ValidationError::DanglingRef { pid, field, target });
+                        }
+                    }
+                }
+            }
+        }
+        r
+    }
+
+    /// Writes the proto index that can be loaded with `load_index()`.
+    pub fn save_index(&self, wr: &mut impl Write) -> io::Result<()> {
+        self.lst.write_index(wr)
+    }
+
     pub fn messages(&self) -> &Messages {
         &self.messages
     }
 
     pub fn proto(&self, pid: ProtoId) -> io::Result<ProtoRef> {
-        let mut protos = self.protos.borrow_mut();
-        match protos.entry(pid) {
-            hash_map::Entry::Occupied(e) => Ok(e.get().clone()),
-            hash_map::Entry::Vacant(e) => {
-                let file_name = self.lst.get(pid)
-                    .ok_or_else(|| Error::new(ErrorKind::InvalidData,
-                        format!("can't find proto file name for {:?}", pid)))?;
-                let path = format!("proto/{}/{}", pid.kind().dir(), file_name);
-
-                let proto = Rc::new(RefCell::new(self.read_proto_file(&path)?));
-                e.insert(proto.clone());
-                Ok(proto)
-            }
+        if pid == ProtoId::DUDE {
+            return Ok(self.dude());
         }
+        self.protos.borrow_mut().get_or_try_insert_with(pid, || {
+            let rd = &mut self.open_proto_file(pid)?;
+            Ok(Rc::new(RefCell::new(Self::read_proto(&self.entity_messages, rd)?)))
+        })
+    }
+
+    /// Loads protos for all `pids` populating the cache. The results are in the order of `pids`.
+    #[cfg(not(feature = "parallel"))]
+    pub fn load_many(&self, pids: &[ProtoId]) -> Vec<io::Result<ProtoRef>> {
+        pids.iter()
+            .map(|&pid| self.proto(pid))
+            .collect()
+    }
+
+    /// Loads protos for all `pids` populating the cache. The results are in the order of `pids`.
+    /// Protos missing from the cache are parsed on multiple threads.
+    #[cfg(feature = "parallel")]
+    pub fn load_many(&self, pids: &[ProtoId]) -> Vec<io::Result<ProtoRef>> {
+        // Files are opened and the cache is updated on this thread, only parsing is parallel.
+        let readers: Vec<_> = pids.iter()
+            .map(|&pid| if pid == ProtoId::DUDE || self.protos.borrow().contains_key(pid) {
+                None
+            } else {
+                Some(self.open_proto_file(pid))
+            })
+            .collect();
+        let entity_messages = &self.entity_messages;
+        let mut parsed: Vec<_> = readers.into_par_iter()
+            .map(|rd| rd.map(|rd| Self::read_proto(entity_messages, &mut rd?)))
+            .collect();
+
+        pids.iter().zip(&mut parsed)
+            .map(|(&pid, parsed)| {
+                if pid == ProtoId::DUDE {
+                    return Ok(self.dude());
+                }
+                self.protos.borrow_mut().get_or_try_insert_with(pid, || {
+                    // The proto might have been evicted since `readers` were collected.
+                    let proto = match parsed.take() {
+                        Some(proto) => proto?,
+                        None => Self::read_proto(&self.entity_messages,
+                            &mut self.open_proto_file(pid)?)?,
+                    };
+                    Ok(Rc::new(RefCell::new(proto)))
+                })
+            })
+            .collect()
     }
 
     pub fn dude(&self) -> ProtoRef {
-        self.protos.borrow().get(&ProtoId::DUDE).unwrap().clone()
+        self.dude.clone()
     }
 
     fn read_entity_messages(fs: &FileSystem, language: &str)
@@ -104,9 +211,17 @@ impl ProtoDb {
         Ok(map)
     }
 
-    fn read_proto_file(&self, path: &str) -> io::Result<Proto> {
-        let rd = &mut self.fs.reader(&path)?;
+    fn open_proto_file(&self, pid: ProtoId) -> io::Result<Box<dyn BufRead + Send>> {
+        let file_name = self.lst.get(pid)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData,
+                format!("can't find proto file name for {:?}", pid)))?;
+        let path = format!("proto/{}/{}", pid.kind().dir(), file_name);
+        self.fs.reader(&path)
+    }
 
+    fn read_proto(entity_messages: &EnumMap<EntityKind, Messages>, rd: &mut impl Read)
+        -> io::Result<Proto>
+    {
         let pid = ProtoId::read(rd)?;
         let message_id = rd.read_i32::<BigEndian>()?;
         let fid = FrameId::read(rd)?;
@@ -159,10 +274,10 @@ impl ProtoDb {
         };
 
         // proto_name()
-        let name = self.msg(pid.kind(), message_id, 0)?
+        let name = Self::msg(entity_messages, pid.kind(), message_id, 0)?
             .map(|s| s.to_owned());
         // proto_description()
-        let description = self.msg(pid.kind(), message_id, 1)?
+        let description = Self::msg(entity_messages, pid.kind(), message_id, 1)?
             .map(|s| s.to_owned());
 
         Ok(Proto {
@@ -523,10 +638,10 @@ impl ProtoDb {
         })
     }
 
-    fn msg(&self, kind: EntityKind, msg_id: MessageId, base: MessageId)
-        -> io::Result<Option<&bstr>>
+    fn msg(entity_messages: &EnumMap<EntityKind, Messages>, kind: EntityKind, msg_id: MessageId,
+        base: MessageId) -> io::Result<Option<&bstr>>
     {
-        Ok(self.entity_messages[kind].get(base + msg_id)
+        Ok(entity_messages[kind].get(base + msg_id)
             .map(|m| m.text.as_ref()))
     }
 }
@@ -551,12 +666,49 @@ impl Lst {
     }
 
     pub fn get(&self, pid: ProtoId) -> Option<&str> {
-        let id = pid.id() as usize;
-        if id > 0 {
-            self.lst[pid.kind()].get(id - 1).map(|e| e.fields[0].as_ref())
-        } else {
-            None
+        self.lst[pid.kind()].get(pid.lst_index()?).map(|e| e.fields[0].as_ref())
+    }
+
+    // Index format: for each proto entity kind the number of entries (u32) followed by
+    // the entries' file names, each prefixed with its length (u16).
+
+    fn read_index(rd: &mut impl Read) -> io::Result<Self> {
+        let mut lst = EnumMap::new();
+        for k in proto_entity_kinds() {
+            let len = rd.read_u32::<BigEndian>()?;
+            let mut entries = Vec::new();
+            for _ in 0..len {
+                let name_len = rd.read_u16::<BigEndian>()?;
+                let mut name = vec![0; name_len as usize];
+                rd.read_exact(&mut name)?;
+                let name = String::from_utf8(name)
+                    .map_err(|_| Error::new(ErrorKind::InvalidData,
+                        "invalid file name in proto index"))?;
+                entries.push(LstEntry {
+                    fields: vec![name],
+                });
+            }
+            lst[k] = entries;
+        }
+        Ok(Self {
+            lst,
+        })
+    }
+
+    fn write_index(&self, wr: &mut impl Write) -> io::Result<()> {
+        for k in proto_entity_kinds() {
+            let entries = &self.lst[k];
+            wr.write_u32::<BigEndian>(entries.len() as u32)?;
+            for e in entries {
+                let name = e.fields[0].as_bytes();
+                let name_len = u16::try_from(name.len())
+                    .map_err(|_| Error::new(ErrorKind::InvalidInput,
+                        "file name too long for proto index"))?;
+                wr.write_u16::<BigEndian>(name_len)?;
+                wr.write_all(name)?;
+            }
         }
+        Ok(())
     }
 
     fn read_lst_file(fs: &FileSystem, kind: EntityKind) -> io::Result<Vec<LstEntry>> {
@@ -584,4 +736,256 @@ fn read_enum<T: FromPrimitive>(rd: &mut impl Read, err: &str) -> io::Result<T> {
 
 fn read_opt_enum<T: FromPrimitive>(rd: &mut impl Read, err: &str) -> io::Result<Option<T>> {
     get_opt_enum(rd.read_i32::<BigEndian>()?, err)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::cell::Cell;
+
+    use super::*;
+
+    fn misc_proto(id: u32, light_radius: i32) -> Vec<u8> {
+        let mut r = Vec::new();
+        r.write_u32::<BigEndian>(ProtoId::new(EntityKind::Misc, id).unwrap().pack()).unwrap();
+        r.write_i32::<BigEndian>(0).unwrap();
+        r.write_u32::<BigEndian>(FrameId::new(EntityKind::Misc, None, 0, 0, 0).unwrap().packed())
+            .unwrap();
+        r.write_i32::<BigEndian>(light_radius).unwrap();
+        for _ in 0..3 {
+            r.write_u32::<BigEndian>(0).unwrap();
+        }
+        r
+    }
+
+    fn misc_item_proto(id: u32, charge_pid: Option<ProtoId>) -> Vec<u8> {
+        let mut r = Vec::new();
+        r.write_u32::<BigEndian>(ProtoId::new(EntityKind::Item, id).unwrap().pack()).unwrap();
+        r.write_i32::<BigEndian>(0).unwrap();
+        r.write_u32::<BigEndian>(FrameId::new(EntityKind::Item, None, 0, 0, 0).unwrap().packed())
+            .unwrap();
+        for _ in 0..4 {
+            r.write_u32::<BigEndian>(0).unwrap();
+        }
+        // Script.
+        r.write_i32::<BigEndian>(-1).unwrap();
+        r.write_u32::<BigEndian>(ItemKind::Misc as u32).unwrap();
+        r.write_u32::<BigEndian>(Material::Metal as u32).unwrap();
+        for _ in 0..3 {
+            r.write_i32::<BigEndian>(1).unwrap();
+        }
+        // Inventory FID.
+        r.write_i32::<BigEndian>(-1).unwrap();
+        r.write_u8(0).unwrap();
+        r.write_i32::<BigEndian>(charge_pid.map(|p| p.pack() as i32).unwrap_or(-1)).unwrap();
+        r.write_u32::<BigEndian>(0).unwrap();
+        r.write_i32::<BigEndian>(10).unwrap();
+        r
+    }
+
+    fn scenery_proto(id: u32, flags: BitFlags<Flag>) -> Vec<u8> {
+        let mut r = Vec::new();
+        r.write_u32::<BigEndian>(ProtoId::new(EntityKind::Scenery, id).unwrap().pack()).unwrap();
+        r.write_i32::<BigEndian>(0).unwrap();
+        r.write_u32::<BigEndian>(
+            FrameId::new(EntityKind::Scenery, None, 0, 0, 0).unwrap().packed()).unwrap();
+        r.write_i32::<BigEndian>(0).unwrap();
+        r.write_i32::<BigEndian>(0).unwrap();
+        r.write_u32::<BigEndian>(flags.bits()).unwrap();
+        r.write_u32::<BigEndian>(0).unwrap();
+        // Script.
+        r.write_i32::<BigEndian>(-1).unwrap();
+        r.write_u32::<BigEndian>(SceneryKind::Misc as u32).unwrap();
+        r.write_u32::<BigEndian>(Material::Stone as u32).unwrap();
+        r.write_u8(0).unwrap();
+        r.write_u32::<BigEndian>(0).unwrap();
+        r
+    }
+
+    fn files(with_lst: bool) -> Vec<(String, Vec<u8>)> {
+        let mut files = Vec::new();
+        for k in proto_entity_kinds() {
+            if with_lst {
+                let lst: &[u8] = match k {
+                    EntityKind::Item => b"00000001.pro\n00000002.pro\n",
+                    EntityKind::Misc => b"00000001.pro\n00000002.pro\n",
+                    EntityKind::Scenery => b"00000001.pro\n00000002.pro\n",
+                    _ => b"",
+                };
+                files.push((format!("proto/{0}/{0}.lst", k.dir()), lst.to_vec()));
+            }
+            files.push((format!("text/english/game/pro_{}.msg", &k.dir()[..4]), Vec::new()));
+        }
+        files.push(("text/english/game/proto.msg".into(), Vec::new()));
+        files.push(("proto/misc/00000001.pro".into(), misc_proto(1, 1)));
+        files.push(("proto/misc/00000002.pro".into(), misc_proto(2, 2)));
+        files.push(("proto/items/00000001.pro".into(),
+            misc_item_proto(1, ProtoId::new(EntityKind::Item, 2))));
+        files.push(("proto/items/00000002.pro".into(),
+            misc_item_proto(2, ProtoId::new(EntityKind::Item, 99))));
+        files.push(("proto/scenery/00000001.pro".into(), scenery_proto(1, BitFlags::empty())));
+        files.push(("proto/scenery/00000002.pro".into(), scenery_proto(2, Flag::NoBlock.into())));
+        files
+    }
+
+    fn mem_fs(with_lst: bool) -> Rc<FileSystem> {
+        let mut fs = FileSystem::new();
+        fs.register_provider(crate::fs::mem::new_provider(files(with_lst)));
+        Rc::new(fs)
+    }
+
+    /// Provider that counts how many times proto files were opened.
+    struct CountingProvider {
+        inner: Box<dyn crate::fs::Provider>,
+        proto_reads: Rc<Cell<usize>>,
+    }
+
+    impl crate::fs::Provider for CountingProvider {
+        fn reader(&self, path: &str) -> io::Result<Box<dyn BufRead + Send>> {
+            if path.ends_with(".pro") {
+                self.proto_reads.set(self.proto_reads.get() + 1);
+            }
+            self.inner.reader(path)
+        }
+
+        fn metadata(&self, path: &str) -> io::Result<crate::fs::Metadata> {
+            self.inner.metadata(path)
+        }
+    }
+
+    /// Same as `proto_db()` but also returns the counter of parsed proto files.
+    fn counting_proto_db() -> (ProtoDb, Rc<Cell<usize>>) {
+        let proto_reads = Rc::new(Cell::new(0));
+        let mut fs = FileSystem::new();
+        fs.register_provider(Box::new(CountingProvider {
+            inner: crate::fs::mem::new_provider(files(true)),
+            proto_reads: proto_reads.clone(),
+        }));
+        (ProtoDb::new(Rc::new(fs), "english").unwrap(), proto_reads)
+    }
+
+    /// Returns proto database with two protos with PIDs 1 and 2 of each of misc, item and scenery
+    /// kinds. Scenery 1 blocks movement, scenery 2 doesn't.
+    pub fn proto_db() -> ProtoDb {
+        ProtoDb::new(mem_fs(true), "english").unwrap()
+    }
+
+    #[test]
+    fn kind_counts() {
+        let counts = proto_db().kind_counts();
+        for (k, &c) in &counts {
+            let expected = match k {
+                EntityKind::Item | EntityKind::Misc | EntityKind::Scenery => 2,
+                _ => 0,
+            };
+            assert_eq!(c, expected);
+        }
+    }
+
+    #[test]
+    fn validate() {
+        let db = proto_db();
+        let item = db.proto(ProtoId::new(EntityKind::Item, 1).unwrap()).unwrap();
+        assert_eq!(item.borrow().sub.as_item().unwrap().sub.as_misc().unwrap().max_ammo_count, 10);
+
+        let errors = db.validate();
+        assert_eq!(errors.len(), 1);
+        match &errors[0] {
+            &ValidationError::DanglingRef { pid, field, target } => {
+                assert_eq!(pid, ProtoId::new(EntityKind::Item, 2).unwrap());
+                assert_eq!(field, "ammo_proto_id");
+                assert_eq!(target, ProtoId::new(EntityKind::Item, 99).unwrap());
+            }
+            e => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn index_round_trip() {
+        let db = proto_db();
+        let mut index = Vec::new();
+        db.save_index(&mut index).unwrap();
+
+        let fs = mem_fs(false);
+        assert!(ProtoDb::new(fs.clone(), "english").is_err());
+        let loaded = ProtoDb::load_index(fs, "english", &mut &index[..]).unwrap();
+
+        for k in proto_entity_kinds() {
+            assert_eq!(loaded.len(k), db.len(k));
+        }
+        for id in 0..4 {
+            let pid = ProtoId::new(EntityKind::Misc, id).unwrap();
+            assert_eq!(loaded.lst.get(pid), db.lst.get(pid));
+            assert_eq!(loaded.proto(pid).ok().map(|p| p.borrow().light_radius),
+                db.proto(pid).ok().map(|p| p.borrow().light_radius));
+        }
+
+        assert!(ProtoDb::load_index(mem_fs(false), "english", &mut &index[..index.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn load_many() {
+        let db = proto_db();
+        let pid = |id| ProtoId::new(EntityKind::Misc, id).unwrap();
+        let r = db.load_many(&[pid(2), pid(99), pid(1)]);
+        assert_eq!(r.len(), 3);
+        assert_eq!(r[0].as_ref().unwrap().borrow().light_radius, 2);
+        assert!(r[1].is_err());
+        assert_eq!(r[2].as_ref().unwrap().borrow().light_radius, 1);
+
+        assert!(Rc::ptr_eq(r[0].as_ref().unwrap(), &db.proto(pid(2)).unwrap()));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn load_many_parallel() {
+        let mut pids: Vec<_> = proto_entity_kinds()
+            .flat_map(|k| (1..=3).map(move |id| ProtoId::new(k, id).unwrap()))
+            .collect();
+        pids.push(ProtoId::DUDE);
+        pids.push(pids[0]);
+
+        let format = |r: &io::Result<ProtoRef>| match r {
+            Ok(p) => format!("{:?}", p.borrow()),
+            Err(e) => format!("{:?}: {}", e.kind(), e),
+        };
+        let serial_db = proto_db();
+        let serial: Vec<_> = pids.iter().map(|&pid| format(&serial_db.proto(pid))).collect();
+
+        for &capacity in &[usize::MAX, 1] {
+            let db = proto_db().with_cache_capacity(capacity);
+            // Make some of the protos cached.
+            db.proto(pids[1]).unwrap();
+            let r = db.load_many(&pids);
+            assert_eq!(r.iter().map(format).collect::<Vec<_>>(), serial);
+            assert!(Rc::ptr_eq(r.last().unwrap().as_ref().unwrap(),
+                &db.proto(*pids.last().unwrap()).unwrap()));
+        }
+    }
+
+    #[test]
+    fn cache_capacity() {
+        let (db, parsed) = counting_proto_db();
+        let db = db.with_cache_capacity(2);
+        let pid = |id| ProtoId::new(EntityKind::Misc, id).unwrap();
+        let pid3 = ProtoId::new(EntityKind::Item, 1).unwrap();
+
+        let p1 = db.proto(pid(1)).unwrap();
+        db.proto(pid(2)).unwrap();
+        assert_eq!(parsed.get(), 2);
+
+        // Touch the oldest entry so pid 2 becomes the least recently used one.
+        assert!(Rc::ptr_eq(&p1, &db.proto(pid(1)).unwrap()));
+        assert_eq!(parsed.get(), 2);
+
+        db.proto(pid3).unwrap();
+        assert_eq!(parsed.get(), 3);
+
+        assert!(Rc::ptr_eq(&p1, &db.proto(pid(1)).unwrap()));
+        assert_eq!(parsed.get(), 3);
+
+        let p2 = db.proto(pid(2)).unwrap();
+        assert_eq!(parsed.get(), 4);
+        assert_eq!(p2.borrow().id(), pid(2));
+        assert_eq!(p2.borrow().light_radius, 2);
+    }
+}
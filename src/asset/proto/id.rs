@@ -128,6 +128,12 @@ impl ProtoId {
         self.0 & 0xffffff
     }
 
+    /// Returns index of the proto file name in the `.lst` file of the proto's `EntityKind`.
+    /// IDs start from 1 so there's no index for ID 0.
+    pub fn lst_index(self) -> Option<usize> {
+        (self.id() as usize).checked_sub(1)
+    }
+
     pub fn is_dude(self) -> bool {
         self == Self::DUDE
     }
@@ -167,4 +173,22 @@ mod test {
 
         assert!(ProtoId::new(EntityKind::Critter, 0).unwrap().is_dude());
     }
+
+    #[test]
+    fn kind_and_index() {
+        for &(kind, id) in &[
+            (EntityKind::Item, 1),
+            (EntityKind::Critter, 0x123),
+            (EntityKind::Scenery, 0xffffff),
+            (EntityKind::Misc, 0x17),
+        ] {
+            let pid = ProtoId::from_packed((kind as u32) << 24 | id).unwrap();
+            assert_eq!(pid.kind(), kind);
+            assert_eq!(pid.id(), id);
+            assert_eq!(pid.lst_index(), Some(id as usize - 1));
+        }
+        assert_eq!(ProtoId::SCROLL_BLOCKER.kind(), EntityKind::Misc);
+        assert_eq!(ProtoId::SCROLL_BLOCKER.id(), 0xc);
+        assert_eq!(ProtoId::new(EntityKind::Misc, 0).unwrap().lst_index(), None);
+    }
 }
\ No newline at end of file
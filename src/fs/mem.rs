@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+use std::io::{BufRead, Cursor, Error, ErrorKind, Result};
+
+use super::{Metadata, Provider};
+
+/// Provider serving files from memory. Paths are matched case-insensitively.
+pub fn new_provider(files: impl IntoIterator<Item=(String, Vec<u8>)>) -> Box<dyn Provider> {
+    Box::new(MemFileSystem {
+        files: files.into_iter()
+            .map(|(path, data)| (normalize(&path), data))
+            .collect(),
+    })
+}
+
+struct MemFileSystem {
+    files: HashMap<String, Vec<u8>>,
+}
+
+impl MemFileSystem {
+    fn file(&self, path: &str) -> Result<&Vec<u8>> {
+        self.files.get(&normalize(path))
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("file not found: {}", path)))
+    }
+}
+
+impl Provider for MemFileSystem {
+    fn reader(&self, path: &str) -> Result<Box<dyn BufRead + Send>> {
+        Ok(Box::new(Cursor::new(self.file(path)?.clone())))
+    }
+
+    fn metadata(&self, path: &str) -> Result<Metadata> {
+        let len = self.file(path)?.len() as u64;
+        Ok(Metadata { len })
+    }
+}
+
+fn normalize(path: &str) -> String {
+    path.replace('\\', "/").to_ascii_lowercase()
+}
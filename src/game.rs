@@ -1,5 +1,7 @@
+pub mod combat;
 pub mod dialog;
 pub mod fidget;
+pub mod movement;
 pub mod object;
 pub mod rpg;
 pub mod script;
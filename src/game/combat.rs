@@ -0,0 +1,176 @@
+use num_traits::clamp;
+use std::cmp;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use crate::asset::{AttackKind, CritterAnim, DamageKind, WeaponKind};
+use crate::asset::frame::{FrameId, FrmHeader};
+use crate::asset::proto::{CritterKillKind, Weapon};
+use crate::graphics::frm::frame_len;
+use crate::graphics::geometry::hex::Direction;
+
+/// Maximum chance to hit in percents.
+pub const MAX_TO_HIT_CHANCE: i32 = 95;
+
+/// Returns chance in percents of hitting the target at `distance` hexes having `target_ac` armor
+/// class, with the primary or `secondary` attack of the `weapon`. The `skill` is the attacker's
+/// level of the weapon skill and `strength` is the attacker's strength. The distance is accounted
+/// by `Weapon::range_modifier()`, targets beyond the weapon range can't be hit. The perception
+/// bonus to the effective range is not included.
+// determine_to_hit_func()
+pub fn to_hit_chance(weapon: &Weapon, secondary: bool, skill: i32, strength: i32, distance: i32,
+    target_ac: i32) -> i32
+{
+    let range_modifier = weapon.range_modifier(secondary, distance);
+    if range_modifier == Weapon::OUT_OF_RANGE {
+        return 0;
+    }
+    let mut r = skill + range_modifier;
+    r -= weapon.strength_penalty(strength);
+    r -= target_ac;
+    clamp(r, 0, MAX_TO_HIT_CHANCE)
+}
+
+/// Returns how long the animation of the primary or `secondary` attack of the `weapon` takes.
+/// `frm` is the critter animation of the attack facing `direction` which is played at `fps`
+/// frames per second. Returns zero if the weapon has no such attack mode.
+pub fn attack_duration(weapon: &Weapon, secondary: bool, frm: &FrmHeader, direction: Direction,
+    fps: u16) -> Duration
+{
+    if weapon.attack(secondary).0 == AttackKind::Stand {
+        return Duration::from_secs(0);
+    }
+    let (direction, _) = frm.resolve_direction(direction);
+    frame_len(fps) * frm.frame_count(direction) as u32
+}
+
+/// Returns damage range dealt by an explosion with `center_damage` at the blast center to targets
+/// at `distance` hexes from the center. The damage falls off linearly with the distance down to
+/// `1 / (radius + 1)` of the center damage at the `radius`. Beyond the `radius` there's no damage.
+pub fn explosion_damage(center_damage: RangeInclusive<i32>, distance: i32, radius: i32)
+    -> RangeInclusive<i32>
+{
+    let distance = cmp::max(distance, 0);
+    if distance > radius {
+        return 0..=0;
+    }
+    let scale = |v: i32| v * (radius + 1 - distance) / (radius + 1);
+    scale(*center_damage.start())..=scale(*center_damage.end())
+}
+
+/// Returns frame ID of the bloody single-frame death pose left on the map after a critter with
+/// `base` art and `kill_kind` is killed with `damage_kind` damage. Returns `None` if the death
+/// doesn't leave blood: for robots or if `base` is not a critter frame ID.
+/// This is simplified compared to the original which also takes the attack, the amount of
+/// damage and the violence level into account.
+// pick_death()
+pub fn death_decal_fid(base: FrameId, kill_kind: CritterKillKind, damage_kind: DamageKind)
+    -> Option<FrameId>
+{
+    if kill_kind == CritterKillKind::Robot {
+        return None;
+    }
+    let anim = match damage_kind {
+        DamageKind::Explosion => CritterAnim::ExplodedToNothingSf,
+        DamageKind::Plasma => CritterAnim::MeltedToNothingSf,
+        DamageKind::Fire => CritterAnim::BurnedToNothingSf,
+        DamageKind::Electric => CritterAnim::ElectrifiedToNothingSf,
+        | DamageKind::Melee
+        | DamageKind::Laser
+        | DamageKind::Emp
+        | DamageKind::Radiation
+        | DamageKind::Poison
+        => CritterAnim::FallBackBloodSf,
+    };
+    Some(base.critter()?
+        .with_anim(anim)
+        .with_weapon(WeaponKind::Unarmed)
+        .into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::asset::EntityKind;
+    use crate::asset::frame::Frm;
+    use crate::asset::frame::test::make_frm;
+    use crate::asset::proto::test::weapon;
+
+    #[test]
+    fn to_hit_chance_() {
+        let mut w = weapon(WeaponKind::Rifle);
+        w.min_strength = 5;
+
+        // Point blank with high skill is capped.
+        assert_eq!(to_hit_chance(&w, false, 150, 5, 1, 0), MAX_TO_HIT_CHANCE);
+        assert_eq!(to_hit_chance(&w, false, 90, 5, 1, 0), 86);
+
+        // Long range with low skill.
+        assert_eq!(to_hit_chance(&w, false, 40, 5, 9, 0), 4);
+        assert_eq!(to_hit_chance(&w, false, 30, 5, 9, 0), 0);
+        assert_eq!(to_hit_chance(&w, false, 150, 5, 11, 0), 0);
+
+        assert_eq!(to_hit_chance(&w, false, 90, 3, 1, 0), 46);
+        assert_eq!(to_hit_chance(&w, false, 90, 5, 1, 20), 66);
+
+        // Melee attacks don't depend on the distance.
+        w.attack_kind.secondary = AttackKind::Swing;
+        assert_eq!(to_hit_chance(&w, true, 90, 5, 20, 10), 80);
+    }
+
+    #[test]
+    fn death_decal_fid_() {
+        let base = FrameId::new_critter(Some(Direction::W), CritterAnim::Stand, WeaponKind::Rifle,
+            0x10).unwrap();
+        let anim = |kill_kind, damage_kind| death_decal_fid(base, kill_kind, damage_kind)
+            .map(|f| f.critter().unwrap().anim());
+
+        let fid = death_decal_fid(base, CritterKillKind::Man, DamageKind::Melee).unwrap();
+        let critter = fid.critter().unwrap();
+        assert_eq!(critter.anim(), CritterAnim::FallBackBloodSf);
+        assert_eq!(critter.weapon(), WeaponKind::Unarmed);
+        assert_eq!(critter.direction(), Some(Direction::W));
+        assert_eq!(critter.id(), 0x10);
+
+        assert_eq!(anim(CritterKillKind::SuperMutant, DamageKind::Explosion),
+            Some(CritterAnim::ExplodedToNothingSf));
+        assert_eq!(anim(CritterKillKind::Woman, DamageKind::Plasma),
+            Some(CritterAnim::MeltedToNothingSf));
+        assert_eq!(anim(CritterKillKind::Robot, DamageKind::Explosion), None);
+        let scenery = FrameId::new_generic(EntityKind::Scenery, 1).unwrap();
+        assert_eq!(death_decal_fid(scenery, CritterKillKind::Man, DamageKind::Melee), None);
+    }
+
+    #[test]
+    fn explosion_damage_() {
+        assert_eq!(explosion_damage(20..=40, 0, 3), 20..=40);
+        assert_eq!(explosion_damage(20..=40, 1, 3), 15..=30);
+        assert_eq!(explosion_damage(20..=40, 2, 3), 10..=20);
+        assert_eq!(explosion_damage(20..=40, 3, 3), 5..=10);
+        assert_eq!(explosion_damage(20..=40, 4, 3), 0..=0);
+        assert_eq!(explosion_damage(20..=40, 1, 0), 0..=0);
+        assert_eq!(explosion_damage(20..=40, 0, 0), 20..=40);
+    }
+
+    #[test]
+    fn attack_duration_() {
+        let frame = || (1, 1, (0, 0), vec![1]);
+        let data = make_frm([0, 0, 0, 1, 1, 1], &[
+            vec![frame(), frame(), frame(), frame(), frame()],
+            vec![(0, 0, (0, 0), vec![]); 5],
+        ]);
+        let frm = Frm::read(&mut &data[..]).unwrap();
+        let frm = frm.header();
+
+        let w = weapon(WeaponKind::Rifle);
+        assert_eq!(attack_duration(&w, false, frm, Direction::E, 10), Duration::from_millis(500));
+        assert_eq!(attack_duration(&w, false, frm, Direction::E, 20), Duration::from_millis(250));
+        assert_eq!(attack_duration(&w, false, frm, Direction::E, 3000),
+            Duration::from_nanos(333_333 * 5));
+        // Empty direction is mirrored.
+        assert_eq!(attack_duration(&w, false, frm, Direction::W, 10), Duration::from_millis(500));
+
+        // No secondary attack.
+        assert_eq!(attack_duration(&w, true, frm, Direction::E, 10), Duration::from_secs(0));
+    }
+}
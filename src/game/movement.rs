@@ -0,0 +1,66 @@
+use std::io;
+
+use crate::asset::EntityKind;
+use crate::asset::proto::{ProtoDb, ProtoId};
+use crate::graphics::EPoint;
+use crate::graphics::map::SpatialIndex;
+
+/// Returns AP cost of stepping onto the `tile` or `None` if the tile is blocked by a wall or
+/// blocking scenery among the static `objects`. Critters and items are not considered blockers.
+/// Returns error if proto of any of the objects at the `tile` can't be loaded.
+pub fn movement_cost(tile: EPoint, db: &ProtoDb, objects: &SpatialIndex<ProtoId>)
+    -> io::Result<Option<i32>>
+{
+    for &pid in objects.at(tile) {
+        let proto = db.proto(pid)?;
+        let proto = proto.borrow();
+        let blocks = match pid.kind() {
+            EntityKind::Scenery => proto.sub.as_scenery().unwrap().blocks_movement(proto.flags),
+            EntityKind::Wall => proto.blocks_movement(),
+            _ => false,
+        };
+        if blocks {
+            return Ok(None);
+        }
+    }
+    Ok(Some(1))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::asset::proto::proto_db;
+    use crate::graphics::Point;
+    use crate::graphics::map::reachable;
+
+    #[test]
+    fn movement_cost_() {
+        let db = proto_db();
+        let scenery = |id| ProtoId::new(EntityKind::Scenery, id).unwrap();
+        let mut objs = SpatialIndex::new();
+        let blocked = Point::new(10, 10).elevated(0);
+        objs.insert(blocked, scenery(1));
+        let passable = Point::new(11, 10).elevated(0);
+        objs.insert(passable, scenery(2));
+
+        let cost = |tile| movement_cost(tile, &db, &objs).unwrap();
+        assert_eq!(cost(Point::new(0, 0).elevated(0)), Some(1));
+        assert_eq!(cost(passable), Some(1));
+        assert_eq!(cost(blocked), None);
+        assert_eq!(cost(blocked.point.elevated(1)), Some(1));
+
+        let r = reachable(Point::new(10, 11), 1, |p| cost(p.elevated(0)));
+        assert_eq!(r.len(), 6);
+        assert_eq!(r.get(&passable.point), Some(&1));
+        assert!(!r.contains_key(&blocked.point));
+    }
+
+    #[test]
+    fn movement_cost_bad_proto() {
+        let db = proto_db();
+        let mut objs = SpatialIndex::new();
+        let tile = Point::new(10, 10).elevated(0);
+        objs.insert(tile, ProtoId::new(EntityKind::Scenery, 99).unwrap());
+        assert!(movement_cost(tile, &db, &objs).is_err());
+    }
+}
@@ -9,7 +9,7 @@ use std::mem;
 use std::rc::Rc;
 
 use crate::asset::*;
-use crate::asset::frame::{FrameId, FrameDb};
+use crate::asset::frame::{self, FrameId, FrameDb};
 use crate::asset::proto::*;
 use crate::asset::script::ProgramId;
 use crate::game::script::{Scripts, ScriptIid};
@@ -257,7 +257,7 @@ impl Object {
     // obj_is_lockable()
     #[must_use]
     pub fn is_locked(&self) -> Option<bool> {
-        self.sub.as_scenery().and_then(|s| s.as_door()).map(|d| d.flags.contains(DoorFlag::Locked))
+        self.sub.as_scenery().and_then(|s| s.as_door()).map(|d| d.is_locked())
             .or_else(|| if self.proto().map(|p| p.kind()) == Some(ExactEntityKind::Item(ItemKind::Container)) {
                 Some(self.updated_flags.contains(UpdatedFlag::Locked))
             } else {
@@ -277,12 +277,7 @@ impl Object {
                 }
             }
             EntityKind::Scenery => {
-                let door = self.sub.as_scenery_mut().unwrap().as_door_mut().unwrap();
-                if locked {
-                    door.flags.insert(DoorFlag::Locked);
-                } else {
-                    door.flags.remove(DoorFlag::Locked);
-                }
+                self.sub.as_scenery_mut().unwrap().as_door_mut().unwrap().set_locked(locked);
             }
             _ => unreachable!(),
         }
@@ -297,8 +292,7 @@ impl Object {
                 self.updated_flags.contains(UpdatedFlag::Jammed)
             }
             EntityKind::Scenery => {
-                let door = self.sub.as_scenery().unwrap().as_door().unwrap();
-                 door.flags.contains(DoorFlag::Jammed)
+                self.sub.as_scenery().unwrap().as_door().unwrap().is_jammed()
             }
             _ => unreachable!(),
         })
@@ -319,12 +313,7 @@ impl Object {
                 }
             }
             EntityKind::Scenery => {
-                let door = self.sub.as_scenery_mut().unwrap().as_door_mut().unwrap();
-                if jammed {
-                    door.flags.insert(DoorFlag::Jammed);
-                } else {
-                    door.flags.remove(DoorFlag::Jammed);
-                }
+                self.sub.as_scenery_mut().unwrap().as_door_mut().unwrap().set_jammed(jammed);
             }
             _ => {}
         }
@@ -428,6 +417,20 @@ impl Object {
         self.find_inventory_item(objects, |o| o.flags.contains(Flag::RightHand))
     }
 
+    /// Returns kind of the weapon held in the right hand or `WeaponKind::Unarmed` if there's none.
+    /// This defines the art of the critter animations.
+    pub fn held_weapon_kind(&self, objects: &Objects) -> WeaponKind {
+        self.in_right_hand(objects)
+            .and_then(|h| objects.get(h).proto().and_then(|p| p.weapon_kind()))
+            .unwrap_or(WeaponKind::Unarmed)
+    }
+
+    /// Returns frame ID of critter `anim` animation with the art of the held weapon.
+    pub fn critter_anim_fid(&self, objects: &Objects, anim: CritterAnim) -> Option<FrameId> {
+        frame::id::critter_anim_fid(self.fid, anim, self.held_weapon_kind(objects),
+            self.fid.direction())
+    }
+
     // inven_worn
     pub fn wearing(&self, objects: &Objects) -> Option<Handle> {
         self.find_inventory_item(objects, |o| o.flags.contains(Flag::Worn))
@@ -1429,6 +1432,40 @@ pub struct Door {
     pub flags: BitFlags<DoorFlag>,
 }
 
+impl Door {
+    pub fn is_open(&self) -> bool {
+        self.flags.contains(DoorFlag::Open)
+    }
+
+    pub fn set_open(&mut self, open: bool) {
+        self.set_flag(DoorFlag::Open, open);
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.flags.contains(DoorFlag::Locked)
+    }
+
+    pub fn set_locked(&mut self, locked: bool) {
+        self.set_flag(DoorFlag::Locked, locked);
+    }
+
+    pub fn is_jammed(&self) -> bool {
+        self.flags.contains(DoorFlag::Jammed)
+    }
+
+    pub fn set_jammed(&mut self, jammed: bool) {
+        self.set_flag(DoorFlag::Jammed, jammed);
+    }
+
+    fn set_flag(&mut self, flag: DoorFlag, value: bool) {
+        if value {
+            self.flags.insert(flag);
+        } else {
+            self.flags.remove(flag);
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, EnumFlags)]
 #[repr(u32)]
 pub enum UpdatedFlag {
@@ -1469,4 +1506,28 @@ mod test {
             Rect::with_points(Point::new(1, -51), Point::new(30, 12))
                 .translate(base));
     }
+
+    #[test]
+    fn door_flags() {
+        let mut door = Door::default();
+        assert!(!door.is_open());
+        assert!(!door.is_locked());
+        assert!(!door.is_jammed());
+
+        door.set_open(true);
+        assert!(door.is_open());
+        assert_eq!(door.flags, DoorFlag::Open);
+
+        door.set_locked(true);
+        door.set_jammed(true);
+        assert!(door.is_locked());
+        assert!(door.is_jammed());
+        assert_eq!(door.flags, DoorFlag::Open | DoorFlag::Locked | DoorFlag::Jammed);
+
+        door.set_open(false);
+        door.set_jammed(false);
+        assert!(!door.is_open());
+        assert!(door.is_locked());
+        assert!(!door.is_jammed());
+    }
 }
\ No newline at end of file
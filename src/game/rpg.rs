@@ -17,6 +17,8 @@ use crate::util::random::*;
 
 use def::*;
 
+pub use def::{SkillDef, StatDef};
+
 const STAT_NAME_MSG_BASE: MessageId = 100;
 const STAT_DESCR_MSG_BASE: MessageId = 200;
 const STAT_LEVEL_DESCR_BASE: MessageId = 300;
@@ -29,6 +31,8 @@ const LEVEL_UP_MSG: MessageId = 600;
 const PERK_NAME_MSG_BASE: MessageId = 101;
 const PERK_DESCR_MSG_BASE: MessageId = 1101;
 
+pub const SKILL_MAX: i32 = 300;
+
 struct Tagged {
     tagged: bool,
     inc_base: bool,
@@ -205,14 +209,7 @@ impl Rpg {
     pub fn skill(&self, skill: Skill, obj: &Object, objs: &Objects) -> i32 {
         let level = obj.proto().unwrap().sub.as_critter().unwrap().skills[skill];
 
-        let def = &self.skill_defs[skill];
-
-        let mut from_stats = self.stat(def.stat1, obj, objs);
-        if let Some(stat) = def.stat2 {
-            from_stats += self.stat(stat, obj, objs);
-        }
-
-        let mut r = def.base + def.stat_multiplier * from_stats + level;
+        let mut r = self.skill_defs[skill].level(level, |stat| self.stat(stat, obj, objs));
 
         if obj.proto_id().unwrap().is_dude() {
             if self.tagged[skill].tagged {
@@ -225,7 +222,7 @@ impl Rpg {
             // TODO r+= skill_game_difficulty()
         }
 
-        cmp::min(r, 300)
+        cmp::min(r, SKILL_MAX)
     }
 
     // stat_result
@@ -82,6 +82,15 @@ pub struct SkillDef {
 }
 
 impl SkillDef {
+    /// Skill level from the skill `points` and the levels of the stats the skill depends on.
+    pub fn level(&self, points: i32, stat: impl Fn(Stat) -> i32) -> i32 {
+        let mut from_stats = stat(self.stat1);
+        if let Some(stat2) = self.stat2 {
+            from_stats += stat(stat2);
+        }
+        self.base + self.stat_multiplier * from_stats + points
+    }
+
     pub fn defaults() -> EnumMap<Skill, Self> {
         use Skill::*;
         use Stat::*;
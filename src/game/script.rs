@@ -144,6 +144,10 @@ impl ScriptPid {
     }
 }
 
+/// Script ID attached to a proto. This is the script program ID which decodes the script kind
+/// and the program index packed by the engine.
+pub type ScriptId = ScriptPid;
+
 impl fmt::Debug for ScriptPid {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "ScriptPId({:?}, {})", self.kind(), self.program_id().val())
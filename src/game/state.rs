@@ -799,7 +799,7 @@ impl GameState {
             }
             false
         } else {
-            if dooro.sub.as_scenery().unwrap().as_door().unwrap().flags.contains(DoorFlag::Open) {
+            if dooro.sub.as_scenery().unwrap().as_door().unwrap().is_open() {
                 return;
             }
             true
@@ -823,14 +823,7 @@ impl GameState {
         {
             {
                 let mut dooro = world.objects_mut().get_mut(door);
-                {
-                    let door = dooro.sub.as_scenery_mut().unwrap().as_door_mut().unwrap();
-                    if open {
-                        door.flags.insert(DoorFlag::Open);
-                    } else {
-                        door.flags.remove(DoorFlag::Open);
-                    }
-                }
+                dooro.sub.as_scenery_mut().unwrap().as_door_mut().unwrap().set_open(open);
                 if open {
                     dooro.flags.insert(Flag::ShootThru | Flag::LightThru | Flag::NoBlock);
                 } else {
@@ -5,6 +5,7 @@ use std::ops::MulAssign;
 
 pub mod color;
 pub mod font;
+pub mod frm;
 pub mod geometry;
 pub mod lighting;
 pub mod map;
@@ -48,6 +49,26 @@ impl Point {
             clamp(self.x, rect.left, rect.right - 1),
             clamp(self.y, rect.top, rect.bottom - 1))
     }
+
+    /// Rounds each component to the nearest multiple of the corresponding `grid` component.
+    /// Values exactly halfway between two multiples are rounded away from zero. Components with
+    /// non-positive grid size are left unchanged.
+    pub fn snap(self, grid: impl Into<Point>) -> Self {
+        fn snap(v: i32, grid: i32) -> i32 {
+            if grid <= 0 {
+                return v;
+            }
+            let rem = v.rem_euclid(grid);
+            let down = v - rem;
+            match (rem * 2).cmp(&grid) {
+                cmp::Ordering::Less => down,
+                cmp::Ordering::Greater => down + grid,
+                cmp::Ordering::Equal => if v < 0 { down } else { down + grid },
+            }
+        }
+        let grid = grid.into();
+        Self::new(snap(self.x, grid.x), snap(self.y, grid.y))
+    }
 }
 
 impl ops::Add for Point {
@@ -139,7 +160,33 @@ impl From<(i32, i32)> for Point {
     }
 }
 
-#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+impl From<[i32; 2]> for Point {
+    fn from(v: [i32; 2]) -> Self {
+        Self::new(v[0], v[1])
+    }
+}
+
+impl From<Point> for [i32; 2] {
+    fn from(v: Point) -> Self {
+        [v.x, v.y]
+    }
+}
+
+/// Points are ordered by `y` and then by `x`.
+impl Ord for Point {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        (self.y, self.x).cmp(&(other.y, other.x))
+    }
+}
+
+impl PartialOrd for Point {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Elevated points are ordered by `elevation` and then by `point`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct EPoint {
     pub elevation: u32,
     pub point: Point,
@@ -176,7 +223,20 @@ impl From<(u32, (i32, i32))> for EPoint {
     }
 }
 
+/// Relation of a rect to another rect. See `Rect::relation()`.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Coverage {
+    /// The other rect is entirely inside this rect.
+    Contains,
+
+    /// The rects intersect but the other rect is not entirely inside this rect.
+    Overlaps,
+
+    /// The rects don't intersect.
+    Disjoint,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct Rect {
     pub left: i32,
     pub top: i32,
@@ -221,6 +281,23 @@ impl Rect {
         }
     }
 
+    /// Same as `with_size()` but returns `None` if `right` or `bottom` overflows.
+    pub fn with_size_checked(left: i32, top: i32, width: i32, height: i32) -> Option<Self> {
+        Some(Self {
+            left,
+            top,
+            right: left.checked_add(width)?,
+            bottom: top.checked_add(height)?,
+        })
+    }
+
+    /// Creates rect with top left corner at `origin`. The `size` is `(width, height)`.
+    pub fn from_point_size(origin: impl Into<Point>, size: impl Into<Point>) -> Self {
+        let origin = origin.into();
+        let size = size.into();
+        Self::with_size(origin.x, origin.y, size.x, size.y)
+    }
+
     pub fn with_points(top_left: Point, bottom_right: Point) -> Self {
         Self {
             left: top_left.x,
@@ -243,6 +320,28 @@ impl Rect {
         }
     }
 
+    /// Expands the rect outwards to the nearest multiples of `grid_w` horizontally and `grid_h`
+    /// vertically. The result always contains this rect.
+    pub fn align_to_grid(&self, grid_w: i32, grid_h: i32) -> Self {
+        assert!(grid_w > 0 && grid_h > 0);
+        let floor = |v: i32, d: i32| v.div_euclid(d) * d;
+        let ceil = |v: i32, d: i32| -(-v).div_euclid(d) * d;
+        Self {
+            left: floor(self.left, grid_w),
+            top: floor(self.top, grid_h),
+            right: ceil(self.right, grid_w),
+            bottom: ceil(self.bottom, grid_h),
+        }
+    }
+
+    /// Returns rows of this rect clipped to the `clip` rect as `(y, left..right)`, top to bottom.
+    /// Yields nothing if the rects don't intersect.
+    pub fn scanlines(&self, clip: &Rect) -> impl Iterator<Item=(i32, ops::Range<i32>)> {
+        let r = self.intersect(*clip);
+        let rows = if r.left < r.right { r.top..r.bottom } else { 0..0 };
+        rows.map(move |y| (y, r.left..r.right))
+    }
+
     pub fn translate(&self, offset: Point) -> Self {
         Self {
             left: self.left + offset.x,
@@ -269,6 +368,18 @@ impl Rect {
             self.bottom > other.top
     }
 
+    /// Returns how this rect covers the `other` rect. Rects that only touch by edges are disjoint.
+    pub fn relation(&self, other: &Rect) -> Coverage {
+        if !self.intersects(*other) {
+            Coverage::Disjoint
+        } else if other.left >= self.left && other.right <= self.right &&
+                other.top >= self.top && other.bottom <= self.bottom {
+            Coverage::Contains
+        } else {
+            Coverage::Overlaps
+        }
+    }
+
     pub fn top_left(&self) -> Point {
         Point::new(self.left, self.top)
     }
@@ -285,8 +396,211 @@ impl Rect {
         self.bottom - self.top
     }
 
+    /// Returns `width * height` or 0 if the rect has no area.
+    pub fn area(&self) -> i64 {
+        cmp::max(self.width() as i64, 0) * cmp::max(self.height() as i64, 0)
+    }
+
+    /// Returns the fraction of this rect area covered by the `other` rect in range `[0..1]`.
+    /// Returns 0 if this rect has no area.
+    pub fn overlap_ratio(&self, other: &Rect) -> f64 {
+        let area = self.area();
+        if area == 0 {
+            return 0.0;
+        }
+        self.intersect(*other).area() as f64 / area as f64
+    }
+
     pub fn center(&self) -> Point {
         Point::new(self.left + self.width() / 2, self.top + self.height() / 2)
     }
+
+    /// Divides the rect into `cols` x `rows` cells returned in row-major order. If the size is
+    /// not divisible the remainder pixels are distributed one per cell starting from the first
+    /// cells. Returns empty vec if `cols` or `rows` is zero.
+    pub fn subdivide(&self, cols: u32, rows: u32) -> Vec<Rect> {
+        fn split(start: i32, len: i32, count: i32) -> Vec<(i32, i32)> {
+            let (base, rem) = (len / count, len % count);
+            let mut r = Vec::with_capacity(count as usize);
+            let mut pos = start;
+            for i in 0..count {
+                let len = base + if i < rem { 1 } else { 0 };
+                r.push((pos, pos + len));
+                pos += len;
+            }
+            r
+        }
+
+        if cols == 0 || rows == 0 {
+            return Vec::new();
+        }
+        let xs = split(self.left, self.width(), cols as i32);
+        let ys = split(self.top, self.height(), rows as i32);
+        let mut r = Vec::with_capacity(xs.len() * ys.len());
+        for &(top, bottom) in &ys {
+            for &(left, right) in &xs {
+                r.push(Rect { left, top, right, bottom });
+            }
+        }
+        r
+    }
+}
+
+/// Array is `[left, top, right, bottom]`.
+impl From<[i32; 4]> for Rect {
+    fn from(v: [i32; 4]) -> Self {
+        Self::new(v[0], v[1], v[2], v[3])
+    }
 }
 
+impl From<Rect> for [i32; 4] {
+    fn from(v: Rect) -> Self {
+        [v.left, v.top, v.right, v.bottom]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn point_snap() {
+        let p = |x, y| Point::new(x, y);
+        assert_eq!(p(11, 13).snap((8, 16)), p(8, 16));
+        assert_eq!(p(13, 7).snap((8, 16)), p(16, 0));
+        assert_eq!(p(-3, -13).snap((8, 8)), p(0, -16));
+        assert_eq!(p(-5, -11).snap((8, 8)), p(-8, -8));
+        assert_eq!(p(16, -24).snap((8, 8)), p(16, -24));
+        assert_eq!(p(4, -4).snap((8, 8)), p(8, -8));
+        assert_eq!(p(5, 7).snap((0, 1)), p(5, 7));
+    }
+
+    #[test]
+    fn rect_with_size_checked() {
+        assert_eq!(Rect::with_size_checked(10, 20, 30, 40), Some(Rect::new(10, 20, 40, 60)));
+        assert_eq!(Rect::with_size_checked(10, 20, i32::MAX, 40), None);
+        assert_eq!(Rect::with_size_checked(10, i32::MAX - 10, 30, 11), None);
+        assert_eq!(Rect::with_size_checked(10, i32::MAX - 10, 30, 10),
+            Some(Rect::new(10, i32::MAX - 10, 40, i32::MAX)));
+    }
+
+    #[test]
+    fn rect_from_point_size() {
+        let r = Rect::from_point_size((10, -5), Point::new(30, 20));
+        assert_eq!(r.left, 10);
+        assert_eq!(r.top, -5);
+        assert_eq!(r.right, 40);
+        assert_eq!(r.bottom, 15);
+    }
+
+    #[test]
+    fn rect_subdivide() {
+        let rect = Rect::with_size(10, 20, 11, 6);
+        let cells = rect.subdivide(3, 2);
+        assert_eq!(cells, vec![
+            Rect::new(10, 20, 14, 23), Rect::new(14, 20, 18, 23), Rect::new(18, 20, 21, 23),
+            Rect::new(10, 23, 14, 26), Rect::new(14, 23, 18, 26), Rect::new(18, 23, 21, 26),
+        ]);
+
+        let area: i32 = cells.iter().map(|c| c.width() * c.height()).sum();
+        assert_eq!(area, rect.width() * rect.height());
+        for (i, a) in cells.iter().enumerate() {
+            assert_eq!(a.intersect(rect), *a);
+            for b in &cells[i + 1..] {
+                assert!(!a.intersects(*b));
+            }
+        }
+
+        assert_eq!(rect.subdivide(1, 1), vec![rect]);
+        assert!(rect.subdivide(0, 2).is_empty());
+        assert!(rect.subdivide(2, 0).is_empty());
+    }
+
+    #[test]
+    fn rect_relation() {
+        let r = Rect::new(0, 0, 10, 10);
+        assert_eq!(r.relation(&Rect::new(2, 2, 5, 5)), Coverage::Contains);
+        assert_eq!(r.relation(&r), Coverage::Contains);
+        assert_eq!(r.relation(&Rect::new(5, 5, 15, 15)), Coverage::Overlaps);
+        assert_eq!(r.relation(&Rect::new(-5, -5, 15, 15)), Coverage::Overlaps);
+        assert_eq!(r.relation(&Rect::new(20, 0, 30, 10)), Coverage::Disjoint);
+
+        // Touching edges.
+        assert_eq!(r.relation(&Rect::new(10, 0, 20, 10)), Coverage::Disjoint);
+        assert_eq!(r.relation(&Rect::new(0, -10, 10, 0)), Coverage::Disjoint);
+    }
+
+    #[test]
+    fn point_ord() {
+        let mut v = vec![Point::new(2, 1), Point::new(-1, 3), Point::new(0, 1), Point::new(5, -2)];
+        v.sort();
+        assert_eq!(v,
+            vec![Point::new(5, -2), Point::new(0, 1), Point::new(2, 1), Point::new(-1, 3)]);
+
+        let mut v = vec![EPoint::new(1, Point::new(0, 0)), EPoint::new(0, Point::new(1, 5)),
+            EPoint::new(0, Point::new(3, 2))];
+        v.sort();
+        assert_eq!(v, vec![EPoint::new(0, Point::new(3, 2)), EPoint::new(0, Point::new(1, 5)),
+            EPoint::new(1, Point::new(0, 0))]);
+    }
+
+    #[test]
+    fn rect_hash() {
+        let mut set = std::collections::HashSet::new();
+        assert!(set.insert(Rect::new(0, 0, 10, 10)));
+        assert!(set.insert(Rect::new(0, 0, 10, 11)));
+        assert!(!set.insert(Rect::with_size(0, 0, 10, 10)));
+        assert!(set.contains(&Rect::new(0, 0, 10, 11)));
+        assert!(!set.contains(&Rect::new(1, 0, 10, 11)));
+    }
+
+    #[test]
+    fn rect_overlap_ratio() {
+        let r = Rect::new(0, 0, 10, 10);
+        assert_eq!(r.area(), 100);
+        assert_eq!(Rect::new(10, 10, 0, 0).area(), 0);
+
+        assert_eq!(r.overlap_ratio(&Rect::new(-5, -5, 20, 20)), 1.0);
+        assert_eq!(r.overlap_ratio(&r), 1.0);
+        assert_eq!(r.overlap_ratio(&Rect::new(5, -5, 20, 20)), 0.5);
+        assert_eq!(r.overlap_ratio(&Rect::new(5, 5, 20, 20)), 0.25);
+        assert_eq!(r.overlap_ratio(&Rect::new(10, 0, 20, 10)), 0.0);
+        assert_eq!(r.overlap_ratio(&Rect::new(50, 50, 60, 60)), 0.0);
+        assert_eq!(Rect::empty().overlap_ratio(&r), 0.0);
+    }
+
+    #[test]
+    fn array_conv() {
+        let p = Point::new(-3, 7);
+        let a: [i32; 2] = p.into();
+        assert_eq!(a, [-3, 7]);
+        assert_eq!(Point::from(a), p);
+
+        let r = Rect::new(1, -2, 30, 40);
+        let a: [i32; 4] = r.into();
+        assert_eq!(a, [1, -2, 30, 40]);
+        assert_eq!(Rect::from(a), r);
+    }
+
+    #[test]
+    fn rect_scanlines() {
+        let clip = Rect::new(0, 0, 10, 10);
+        let r: Vec<_> = Rect::new(-5, 8, 3, 15).scanlines(&clip).collect();
+        assert_eq!(r, vec![(8, 0..3), (9, 0..3)]);
+
+        let r: Vec<_> = Rect::new(2, 3, 5, 5).scanlines(&clip).collect();
+        assert_eq!(r, vec![(3, 2..5), (4, 2..5)]);
+
+        assert_eq!(Rect::new(10, 0, 20, 10).scanlines(&clip).count(), 0);
+        assert_eq!(Rect::new(0, -5, 10, 0).scanlines(&clip).count(), 0);
+    }
+
+    #[test]
+    fn rect_align_to_grid() {
+        let r = Rect::new(-32, 0, 64, 24);
+        assert_eq!(r.align_to_grid(32, 12), r);
+
+        assert_eq!(Rect::new(-5, 3, 33, 13).align_to_grid(32, 12), Rect::new(-32, 0, 64, 24));
+        assert_eq!(Rect::new(1, -13, 31, -1).align_to_grid(32, 12), Rect::new(0, -24, 32, 0));
+    }
+}
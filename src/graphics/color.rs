@@ -263,6 +263,17 @@ impl<P: ColorPrecision> Rgb<P> {
         Self::new(r as u8, g as u8, b as u8)
     }
 
+    /// Scales the color components by light `intensity`. 0x10000 (the maximum light intensity)
+    /// gives the original color, 0 (or negative) gives black. Values above the maximum brighten
+    /// the color saturating each component at its maximum value.
+    #[inline(always)]
+    pub fn scale_intensity(self, intensity: i32) -> Self {
+        let (r, g, b) = self.colors_u32();
+        let f = cmp::max(intensity, 0) as u64;
+        let scale = |c: u32| cmp::min((c as u64 * f) >> 16, P::MASK as u64) as u8;
+        Self::new(scale(r), scale(g), scale(b))
+    }
+
     // additive blending
     #[inline(always)]
     pub fn blend(self, other: Self, q: impl FnOnce(Self) -> Self) -> Self {
@@ -309,6 +320,16 @@ impl<P: ColorPrecision> Rgb<P> {
             ((g * alpha + og * (7 - alpha)) / 7) as u8,
             ((b * alpha + ob * (7 - alpha)) / 7) as u8)
     }
+
+    // alpha is [0..255], alpha 0 - opaque other, alpha 255 - opaque self
+    #[inline(always)]
+    pub fn mix(self, other: Self, alpha: u8) -> Self {
+        let (r, g, b) = self.colors_u32();
+        let (or, og, ob) = other.colors_u32();
+        let alpha = alpha as u32;
+        let mix = |c: u32, oc: u32| ((c * alpha + oc * (255 - alpha) + 127) / 255) as u8;
+        Self::new(mix(r, or), mix(g, og), mix(b, ob))
+    }
 }
 
 impl<P: ColorPrecision> fmt::Debug for Rgb<P> {
@@ -373,6 +394,27 @@ mod test_rgb {
         }
     }
 
+    #[test]
+    fn scale_intensity() {
+        let c = rgb15(5, 10, 29);
+        assert_eq!(c.scale_intensity(0), rgb15(0, 0, 0));
+        assert_eq!(c.scale_intensity(-100), rgb15(0, 0, 0));
+        assert_eq!(c.scale_intensity(0x8000), rgb15(2, 5, 14));
+        assert_eq!(c.scale_intensity(0x10000), c);
+        assert_eq!(c.scale_intensity(0x20000), rgb15(10, 20, 31));
+        assert_eq!(c.scale_intensity(i32::MAX), rgb15(31, 31, 31));
+    }
+
+    #[test]
+    fn mix() {
+        let c1 = Rgb24::new(200, 13, 255);
+        let c2 = Rgb24::new(10, 101, 0);
+        assert_eq!(c1.mix(c2, 0), c2);
+        assert_eq!(c1.mix(c2, 255), c1);
+        assert_eq!(c1.mix(c2, 128), Rgb24::new(105, 57, 128));
+        assert_eq!(rgb15(31, 0, 7).mix(rgb15(0, 31, 7), 64), rgb15(8, 23, 7));
+    }
+
     #[test]
     fn blend_quantize() {
         assert_eq!(rgb15(20, 30, 31).blend(rgb15(12, 13, 14),
@@ -19,6 +19,17 @@ impl Palette {
         }
     }
 
+    /// Returns palette where color index `i` has the color of the index `table[i]` of this palette.
+    /// The reverse lookup used by `color_idx()` is kept as is.
+    pub fn remap(&self, table: &[u8; 256]) -> Palette {
+        let mut r = self.clone();
+        for (i, &src) in table.iter().enumerate() {
+            r.color_idx_to_rgb18[i] = self.color_idx_to_rgb18[src as usize];
+            r.mapped_colors[i] = self.mapped_colors[src as usize];
+        }
+        r
+    }
+
     pub fn rgb<P: ColorPrecision>(&self, color_idx: u8) -> Rgb<P> {
         self.rgb18(color_idx).scale()
     }
@@ -31,10 +42,33 @@ impl Palette {
         self.color_idx_to_rgb18[color_idx as usize]
     }
 
+    /// Returns color with components expanded to the full 8-bit range so the brightest
+    /// component value maps to 255. Unlike `rgb::<Color8>()` doesn't leave the low bits empty.
+    pub fn rgb24(&self, color_idx: u8) -> Rgb24 {
+        let (r, g, b) = self.rgb18(color_idx).colors();
+        let expand = |v: u8| ((v as u32 * 255 + Color6::MASK / 2) / Color6::MASK) as u8;
+        Rgb::new(expand(r), expand(g), expand(b))
+    }
+
     pub fn color_idx<P: ColorPrecision>(&self, rgb: Rgb<P>) -> u8 {
         self.rgb15_to_color_idx[rgb.scale::<Color5>().pack() as usize]
     }
 
+    /// Returns index of the palette color closest to `rgb` by the sum of squared component
+    /// differences. Unmapped colors are never returned. The color index 0 is transparent in FRMs
+    /// and is only returned if `allow_transparent` is `true`.
+    pub fn nearest_index(&self, rgb: Rgb24, allow_transparent: bool) -> u8 {
+        let sqr = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        let start = if allow_transparent { 0 } else { 1 };
+        (start..=255u8)
+            .filter(|&i| self.mapped_colors[i as usize])
+            .min_by_key(|&i| {
+                let c = self.rgb24(i);
+                sqr(c.r(), rgb.r()) + sqr(c.g(), rgb.g()) + sqr(c.b(), rgb.b())
+            })
+            .unwrap_or(start)
+    }
+
     pub fn quantize<P: ColorPrecision>(&self, rgb: Rgb<P>) -> Rgb<P> {
         self.rgb(self.color_idx(rgb))
     }
@@ -96,6 +130,13 @@ impl Palette {
     }
 }
 
+/// Replaces each color index `i` in `pixels` with `table[i]`.
+pub fn remap_pixels(pixels: &mut [u8], table: &[u8; 256]) {
+    for p in pixels {
+        *p = table[*p as usize];
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -130,5 +171,48 @@ mod test {
         }
     }
 
+    #[test]
+    fn remap() {
+        let pal = palette();
+        let mut table = [0; 256];
+        for (i, v) in table.iter_mut().enumerate() {
+            *v = i as u8;
+        }
+
+        let identity = pal.remap(&table);
+        for i in 0..=255 {
+            assert_eq!(identity.rgb18(i), pal.rgb18(i));
+        }
+        let mut pixels = [0, 1, 2, 255];
+        remap_pixels(&mut pixels, &table);
+        assert_eq!(pixels, [0, 1, 2, 255]);
+
+        table.swap(1, 2);
+        let swapped = pal.remap(&table);
+        assert_eq!(swapped.rgb18(1), pal.rgb18(2));
+        assert_eq!(swapped.rgb18(2), pal.rgb18(1));
+        assert_eq!(swapped.rgb18(3), pal.rgb18(3));
+        remap_pixels(&mut pixels, &table);
+        assert_eq!(pixels, [0, 2, 1, 255]);
+    }
 
+    #[test]
+    fn nearest_index() {
+        let mut colors = [Rgb18::black(); 256];
+        let mut mapped = [false; 256];
+        for &(i, r, g, b) in &[(0, 0, 0, 0), (1, 63, 0, 0), (2, 0, 63, 0), (3, 0, 0, 63),
+                (4, 63, 63, 63)] {
+            colors[i] = Rgb::new(r, g, b);
+            mapped[i] = true;
+        }
+        let pal = Palette::new(colors, [0; 32768], mapped);
+
+        assert_eq!(pal.nearest_index(Rgb24::new(200, 40, 30), false), 1);
+        assert_eq!(pal.nearest_index(Rgb24::new(20, 30, 180), false), 3);
+        assert_eq!(pal.nearest_index(Rgb24::new(180, 190, 170), false), 4);
+
+        // Unmapped black entries are skipped.
+        assert_eq!(pal.nearest_index(Rgb24::new(10, 10, 10), false), 1);
+        assert_eq!(pal.nearest_index(Rgb24::new(10, 10, 10), true), 0);
+    }
 }
\ No newline at end of file
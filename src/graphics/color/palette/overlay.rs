@@ -63,6 +63,17 @@ impl PaletteOverlay {
             range.rotate(time);
         }
     }
+
+    /// Returns copy of the `palette` with the current overlay colors baked in.
+    pub fn apply(&self, palette: &Palette) -> Palette {
+        let mut r = palette.clone();
+        for range in &self.ranges {
+            for color_idx in range.start..range.end() {
+                r.color_idx_to_rgb18[color_idx as usize] = range.get(color_idx);
+            }
+        }
+        r
+    }
 }
 
 #[derive(Debug)]
@@ -172,4 +183,31 @@ mod test {
         assert_eq!(t.get(51), Some(Rgb18::new(2, 2, 2)));
         assert_eq!(t.get(100), Some(Rgb18::new(5, 5, 5)));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn apply() {
+        let pal = Palette::new([Rgb18::new(1, 2, 3); 256], [0; 32768], [true; 256]);
+        let mut t = PaletteOverlay::standard();
+        let colors = |p: &Palette| (0..=255).map(|i| p.rgb18(i)).collect::<Vec<_>>();
+
+        let initial = t.apply(&pal);
+        assert_eq!(initial.rgb18(0), Rgb18::new(1, 2, 3));
+        assert_eq!(initial.rgb18(SLIME_PALETTE_START), SLIME[0]);
+        assert_eq!(initial.rgb18(FAST_FIRE_PALETTE_START + 1), FAST_FIRE[1]);
+
+        let tm = Instant::now();
+        t.rotate(tm);
+        let rotated = t.apply(&pal);
+        assert_eq!(rotated.rgb18(0), Rgb18::new(1, 2, 3));
+        assert_eq!(rotated.rgb18(SLIME_PALETTE_START), SLIME[SLIME_LEN - 1]);
+        assert_eq!(rotated.rgb18(FAST_FIRE_PALETTE_START + 1), FAST_FIRE[0]);
+        assert_ne!(colors(&rotated), colors(&initial));
+
+        // Least common multiple of all range lengths.
+        let full_cycle = 60;
+        for i in 1..full_cycle {
+            t.rotate(tm + Duration::from_secs(i));
+        }
+        assert_eq!(colors(&t.apply(&pal)), colors(&initial));
+    }
+}
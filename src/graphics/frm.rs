@@ -0,0 +1,169 @@
+use std::cmp;
+use std::time::Duration;
+
+use crate::graphics::geometry::hex::Direction;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AnimMode {
+    /// After the last frame the animation continues from the first frame.
+    Loop,
+
+    /// The animation stops at the last frame.
+    Once,
+}
+
+/// Returns duration of a single frame of animation playing with `fps` frames per second.
+/// Zero `fps` is treated as 1.
+pub fn frame_len(fps: u16) -> Duration {
+    Duration::from_secs(1) / cmp::max(fps, 1) as u32
+}
+
+/// Advances frames of FRM animation over time.
+#[derive(Clone, Debug)]
+pub struct AnimPlayer {
+    pub direction: Direction,
+    pub mode: AnimMode,
+    frame: usize,
+    frame_count: usize,
+    elapsed: Duration,
+    done: bool,
+}
+
+impl AnimPlayer {
+    pub fn new(direction: Direction, frame_count: usize, mode: AnimMode) -> Self {
+        assert!(frame_count > 0);
+        Self {
+            direction,
+            mode,
+            frame: 0,
+            frame_count,
+            elapsed: Duration::from_secs(0),
+            done: false,
+        }
+    }
+
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    /// Whether the animation in `Once` mode has finished. This happens when the last frame has
+    /// been shown for a full frame length, not as soon as the last frame is reached.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Rewinds to the first frame.
+    pub fn reset(&mut self) {
+        self.frame = 0;
+        self.elapsed = Duration::from_secs(0);
+        self.done = false;
+    }
+
+    /// Advances the animation by `dt` time playing with `fps` frames per second.
+    /// Returns `true` if the current frame has changed.
+    pub fn advance(&mut self, dt: Duration, fps: u16) -> bool {
+        if self.done {
+            return false;
+        }
+        let frame_len = frame_len(fps);
+        let start_frame = self.frame;
+        self.elapsed += dt;
+        while self.elapsed >= frame_len {
+            self.elapsed -= frame_len;
+            if self.frame + 1 < self.frame_count {
+                self.frame += 1;
+            } else {
+                match self.mode {
+                    AnimMode::Loop => self.frame = 0,
+                    AnimMode::Once => {
+                        self.done = true;
+                        self.elapsed = Duration::from_secs(0);
+                        break;
+                    }
+                }
+            }
+            if frame_len == Duration::from_secs(0) {
+                // Zero frame length advances a single frame instead of looping forever.
+                self.elapsed = Duration::from_secs(0);
+                break;
+            }
+        }
+        self.frame != start_frame
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn loop_() {
+        let mut p = AnimPlayer::new(Direction::E, 3, AnimMode::Loop);
+        let frame_len = Duration::from_millis(100);
+        assert_eq!(p.frame(), 0);
+
+        assert!(!p.advance(Duration::from_millis(50), 10));
+        assert_eq!(p.frame(), 0);
+        assert!(p.advance(Duration::from_millis(50), 10));
+        assert_eq!(p.frame(), 1);
+        assert!(p.advance(frame_len, 10));
+        assert_eq!(p.frame(), 2);
+        assert!(p.advance(frame_len, 10));
+        assert_eq!(p.frame(), 0);
+        assert!(!p.is_done());
+
+        // Skips frames if lagging.
+        assert!(p.advance(frame_len * 2, 10));
+        assert_eq!(p.frame(), 2);
+        assert!(!p.advance(frame_len * 3, 10));
+        assert_eq!(p.frame(), 2);
+    }
+
+    #[test]
+    fn once() {
+        let mut p = AnimPlayer::new(Direction::E, 3, AnimMode::Once);
+        let frame_len = Duration::from_millis(100);
+
+        assert!(p.advance(frame_len, 10));
+        assert!(p.advance(frame_len, 10));
+        assert_eq!(p.frame(), 2);
+        assert!(!p.is_done());
+
+        assert!(!p.advance(frame_len, 10));
+        assert_eq!(p.frame(), 2);
+        assert!(p.is_done());
+        assert!(!p.advance(frame_len * 10, 10));
+        assert_eq!(p.frame(), 2);
+
+        p.reset();
+        assert_eq!(p.frame(), 0);
+        assert!(!p.is_done());
+        assert!(p.advance(frame_len * 10, 10));
+        assert_eq!(p.frame(), 2);
+        assert!(p.is_done());
+    }
+
+    #[test]
+    fn large_fps() {
+        assert_eq!(frame_len(0), Duration::from_secs(1));
+        assert_eq!(frame_len(2000), Duration::from_micros(500));
+        assert_eq!(frame_len(u16::MAX), Duration::from_nanos(15259));
+
+        let mut p = AnimPlayer::new(Direction::E, 3, AnimMode::Loop);
+        assert!(!p.advance(Duration::from_micros(499), 2000));
+        assert_eq!(p.frame(), 0);
+        assert!(p.advance(Duration::from_micros(1), 2000));
+        assert_eq!(p.frame(), 1);
+        assert!(p.advance(Duration::from_micros(500), 2000));
+        assert_eq!(p.frame(), 2);
+
+        let mut p = AnimPlayer::new(Direction::E, 3, AnimMode::Once);
+        assert!(p.advance(Duration::from_millis(1), u16::MAX));
+        assert_eq!(p.frame(), 2);
+        assert!(p.is_done());
+    }
+}
@@ -34,4 +34,129 @@ fn from_screen_rect(rect: Rect, from_screen: impl Fn(Point) -> Point) -> Rect {
     let bottom_right_incl = Point::new(x, y);
 
     Rect::with_points(top_left, bottom_right_incl + Point::new(1, 1))
-}
\ No newline at end of file
+}
+
+/// Sorts linear tile numbers in back-to-front screen order: by screen `y` and then by screen `x`.
+/// `from_linear` converts linear tile number to tile coordinates.
+pub fn tiles_in_draw_order(tiles: impl IntoIterator<Item=u32>, view: &impl TileGridView,
+        from_linear: impl Fn(u32) -> Point) -> Vec<u32> {
+    let mut r: Vec<_> = tiles.into_iter()
+        .map(|t| {
+            let p = view.to_screen(from_linear(t));
+            (p.y, p.x, t)
+        })
+        .collect();
+    r.sort();
+    r.into_iter().map(|(_, _, t)| t).collect()
+}
+
+/// Same as `hex::arc()` but works with tile numbers as stored in the original assets.
+/// Tiles outside of the default tile grid are skipped.
+pub fn arc_tiles(center: u32, facing: hex::Direction, spread: u32) -> Vec<u32> {
+    hex::arc(hex::tile_to_point(center), facing, spread).into_iter()
+        .filter_map(hex::point_to_tile)
+        .collect()
+}
+
+/// Clips the line segment from `a` to `b` (both inclusive) to the `rect` using Liang-Barsky
+/// algorithm. Returns `None` if no part of the segment is inside the `rect`. Endpoints that are
+/// inside the `rect` are returned unchanged.
+pub fn clip_segment(a: Point, b: Point, rect: &Rect) -> Option<(Point, Point)> {
+    if rect.left >= rect.right || rect.top >= rect.bottom {
+        return None;
+    }
+    let (left, top) = (rect.left as f64, rect.top as f64);
+    let (right, bottom) = ((rect.right - 1) as f64, (rect.bottom - 1) as f64);
+    let (ax, ay) = (a.x as f64, a.y as f64);
+    let dx = (b.x - a.x) as f64;
+    let dy = (b.y - a.y) as f64;
+
+    let mut t0 = 0.0;
+    let mut t1 = 1.0;
+    for &(p, q) in &[(-dx, ax - left), (dx, right - ax), (-dy, ay - top), (dy, bottom - ay)] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                if t > t1 {
+                    return None;
+                }
+                if t > t0 {
+                    t0 = t;
+                }
+            } else {
+                if t < t0 {
+                    return None;
+                }
+                if t < t1 {
+                    t1 = t;
+                }
+            }
+        }
+    }
+
+    let at = |t: f64| Point::new((ax + t * dx).round() as i32, (ay + t * dy).round() as i32)
+        .clamp_in_rect(*rect);
+    let ca = if t0 > 0.0 { at(t0) } else { a };
+    let cb = if t1 < 1.0 { at(t1) } else { b };
+    Some((ca, cb))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tiles_in_draw_order_() {
+        let tile_grid = sqr::TileGrid::default();
+        let view = sqr::View::new(Point::new(0, 0));
+        let from_linear = |t| tile_grid.from_linear_inv(t);
+        assert_eq!(tiles_in_draw_order(vec![99, 98, 199, 198], &view, from_linear),
+            vec![98, 99, 198, 199]);
+        assert_eq!(tiles_in_draw_order(vec![199, 97, 98, 299, 99], &view, from_linear),
+            vec![97, 98, 99, 199, 299]);
+    }
+
+    #[test]
+    fn arc_tiles_() {
+        use hex::Direction::*;
+        let center = hex::point_to_tile(Point::new(10, 10)).unwrap();
+        let tile = |x, y| hex::point_to_tile(Point::new(x, y)).unwrap();
+
+        assert_eq!(arc_tiles(center, E, 0), vec![tile(11, 10)]);
+        let arc = arc_tiles(center, E, 1);
+        assert_eq!(arc.len(), 3);
+        assert_eq!(arc, hex::arc(Point::new(10, 10), E, 1).into_iter()
+            .map(|p| hex::point_to_tile(p).unwrap())
+            .collect::<Vec<_>>());
+
+        // Off-grid tiles are skipped.
+        assert_eq!(arc_tiles(0, NE, 0), vec![]);
+    }
+
+    #[test]
+    fn clip_segment_() {
+        let rect = Rect::new(0, 0, 100, 50);
+        let a = Point::new(10, 10);
+        let b = Point::new(90, 40);
+
+        assert_eq!(clip_segment(a, b, &rect), Some((a, b)));
+        assert_eq!(clip_segment(b, a, &rect), Some((b, a)));
+
+        assert_eq!(clip_segment(Point::new(-10, -10), Point::new(-1, 60), &rect), None);
+        assert_eq!(clip_segment(Point::new(100, 0), Point::new(200, 49), &rect), None);
+        assert_eq!(clip_segment(Point::new(-20, 10), Point::new(10, -20), &rect), None);
+
+        assert_eq!(clip_segment(Point::new(-50, 20), Point::new(150, 20), &rect),
+            Some((Point::new(0, 20), Point::new(99, 20))));
+        assert_eq!(clip_segment(a, Point::new(10, 100), &rect),
+            Some((a, Point::new(10, 49))));
+        assert_eq!(clip_segment(Point::new(-10, -10), Point::new(20, 20), &rect),
+            Some((Point::new(0, 0), Point::new(20, 20))));
+
+        assert_eq!(clip_segment(a, b, &Rect::empty()), None);
+    }
+}
@@ -32,6 +32,34 @@ impl Camera {
     }
 }
 
+/// Camera origin that can be scrolled by fractional amounts of pixels. The fractional part is kept
+/// between the scrolls so slow scrolling at non-integer speeds doesn't drift.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SmoothOrigin {
+    x: f64,
+    y: f64,
+}
+
+impl SmoothOrigin {
+    pub fn new(origin: Point) -> Self {
+        Self {
+            x: origin.x as f64,
+            y: origin.y as f64,
+        }
+    }
+
+    /// Moves the origin by `dx` and `dy` pixels.
+    pub fn scroll(&mut self, dx: f64, dy: f64) {
+        self.x += dx;
+        self.y += dy;
+    }
+
+    /// Returns the origin rounded to the nearest pixel to be used for rendering.
+    pub fn visible_origin(&self) -> Point {
+        Point::new(self.x.round() as i32, self.y.round() as i32)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -62,4 +90,23 @@ mod test {
             assert_eq!(c.sqr().to_screen(p / 2), expected_sqr);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn smooth_origin() {
+        let mut o = SmoothOrigin::new(Point::new(10, -10));
+        assert_eq!(o.visible_origin(), Point::new(10, -10));
+
+        let mut xs = Vec::new();
+        for _ in 0..8 {
+            o.scroll(0.25, -0.25);
+            xs.push(o.visible_origin().x);
+        }
+        assert_eq!(xs, &[10, 11, 11, 11, 11, 12, 12, 12]);
+        assert_eq!(o.visible_origin(), Point::new(12, -12));
+
+        o.scroll(-0.4, 0.0);
+        assert_eq!(o.visible_origin(), Point::new(12, -12));
+        o.scroll(-0.4, 0.0);
+        assert_eq!(o.visible_origin(), Point::new(11, -12));
+    }
+}
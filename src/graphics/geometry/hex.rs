@@ -37,6 +37,29 @@ impl Direction {
         }
         Self::from_ordinal(o as usize)
     }
+
+    /// Returns direction pointing the other way: `NE` becomes `SW`, `E` becomes `W` etc.
+    pub fn opposite(self) -> Self {
+        Self::from_ordinal((self.ordinal() + Self::len() / 2) % Self::len())
+    }
+
+    /// Returns the shortest rotation from this direction to the `other` in 60 degree steps.
+    /// Positive values are clockwise, negative are counter-clockwise. The opposite direction
+    /// is 3 steps clockwise.
+    pub fn angle_to(self, other: Self) -> i32 {
+        let len = Self::len() as i32;
+        let d = (other.ordinal() as i32 - self.ordinal() as i32 + len) % len;
+        if d > len / 2 {
+            d - len
+        } else {
+            d
+        }
+    }
+
+    /// Returns direction mirrored horizontally: `NE` becomes `NW`, `E` becomes `W` etc.
+    pub fn mirror_x(self) -> Self {
+        Self::from_ordinal(Self::len() - 1 - self.ordinal())
+    }
 }
 
 impl Default for Direction {
@@ -82,6 +105,52 @@ fn go0(mut p: Point, direction: Direction, distance: u32, is_in_bounds: impl Fn(
     p
 }
 
+/// Returns tiles adjacent to `p` that are within `spread` directions of `facing` on either side.
+/// The facing tile goes first, followed by pairs of clockwise and counter-clockwise tiles
+/// moving away from `facing`. `spread` above 3 is the same as 3 and returns all six neighbors.
+pub fn arc(p: Point, facing: Direction, spread: u32) -> Vec<Point> {
+    let spread = cmp::min(spread, 3);
+    let mut r = Vec::with_capacity(spread as usize * 2 + 1);
+    r.push(go(p, facing, 1));
+    let mut cw = facing;
+    let mut ccw = facing;
+    for _ in 0..spread {
+        cw = cw.rotate_cw();
+        ccw = ccw.rotate_ccw();
+        r.push(go(p, cw, 1));
+        if cw != ccw {
+            r.push(go(p, ccw, 1));
+        }
+    }
+    r
+}
+
+/// Returns tiles at exactly `radius` distance from `center` going clockwise starting from the
+/// tile in `NE` direction. Radius 0 yields only the `center`.
+pub fn ring(center: Point, radius: u32) -> impl Iterator<Item=Point> {
+    let mut r = Vec::with_capacity(cmp::max(radius as usize * 6, 1));
+    if radius == 0 {
+        r.push(center);
+    } else {
+        let mut p = go(center, Direction::NE, radius);
+        for dir in Direction::iter() {
+            // Edge between the corners in `dir` and in next direction goes 120 degrees from `dir`.
+            let edge_dir = dir.rotate_cw().rotate_cw();
+            for _ in 0..radius {
+                r.push(p);
+                p = go(p, edge_dir, 1);
+            }
+        }
+    }
+    r.into_iter()
+}
+
+/// Returns tiles within `radius` distance from `center` ring by ring starting from
+/// the `center`. See `ring()` for the order within each ring.
+pub fn spiral(center: Point, radius: u32) -> impl Iterator<Item=Point> {
+    (0..=radius).flat_map(move |r| ring(center, r))
+}
+
 /// Returns tile that is directly above or below the tile at `p` in screen space.
 /// The `offset` defines the number of steps to go up if negative or down if positive.
 pub fn go_vert(p: Point, offset: i32) -> Point {
@@ -204,6 +273,40 @@ impl Iterator for Ray {
     }
 }
 
+/// Returns `true` if none of the tiles intersected by the line between `from` and `to` tile centers
+/// blocks sight. The `from` and `to` tiles themselves are not tested with `blocks_sight`.
+pub fn line_of_sight(from: Point, to: Point, blocks_sight: impl Fn(Point) -> bool) -> bool {
+    if from == to {
+        return true;
+    }
+    ray(from, to)
+        .skip(1)
+        .take_while(|&p| p != to)
+        .all(|p| !blocks_sight(p))
+}
+
+/// Returns tile where `target` ends up after being knocked back `distance` tiles directly away
+/// from `from`. Stops before the first tile that is `blocked`. If `from` and `target` are the same
+/// tile the `target` is returned.
+// compute_knockback()
+pub fn knockback_tile(target: Point, from: Point, distance: u32, blocked: impl Fn(Point) -> bool)
+    -> Point
+{
+    if target == from {
+        return target;
+    }
+    let dir = direction(from, target);
+    let mut r = target;
+    for _ in 0..distance {
+        let next = go(r, dir, 1);
+        if blocked(next) {
+            break;
+        }
+        r = next;
+    }
+    r
+}
+
 /// Casts line between two tile centers and returns coordinates of tile that is `n`-th distinct
 /// intersection of line and tiles that lie beyond and including `from`
 /// if going straight from `from` to `to`, where `n` is the `distance`.
@@ -389,6 +492,19 @@ pub fn is_to_right_of(p1: Point, p2: Point) -> bool {
     sp1.x - sp2_.x <= (sp1.y - sp2_.y) * 32 / (12 * 2)
 }
 
+/// Converts tile number as stored in the original assets to tile coordinates on the default
+/// tile grid. Same as `TileGrid::default().from_linear_inv()`.
+pub fn tile_to_point(tile: u32) -> Point {
+    TileGrid::default().from_linear_inv(tile)
+}
+
+/// Converts tile coordinates on the default tile grid to tile number as stored in the original
+/// assets. Returns `None` if `p` is outside of the grid.
+/// Same as `TileGrid::default().to_linear_inv()`.
+pub fn point_to_tile(p: Point) -> Option<u32> {
+    TileGrid::default().to_linear_inv(p)
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 enum TileHit {
     Inside,
@@ -449,6 +565,25 @@ impl TileGrid {
         }
     }
 
+    /// Same as `hex::ring()` but skips tiles outside of the grid.
+    pub fn ring(&self, center: Point, radius: u32) -> impl Iterator<Item=Point> + '_ {
+        ring(center, radius).filter(move |&p| self.is_in_bounds(p))
+    }
+
+    /// Same as `hex::spiral()` but skips tiles outside of the grid.
+    pub fn spiral(&self, center: Point, radius: u32) -> impl Iterator<Item=Point> + '_ {
+        spiral(center, radius).filter(move |&p| self.is_in_bounds(p))
+    }
+
+    /// Returns tiles adjacent to `p` in `Direction` order. Neighbors outside of the grid are `None`.
+    pub fn neighbors(&self, p: Point) -> [Option<Point>; 6] {
+        let mut r = [None; 6];
+        for dir in Direction::iter() {
+            r[dir.ordinal()] = self.go(p, dir, 1);
+        }
+        r
+    }
+
     pub fn go_clipped(&self, p: Point, direction: Direction, distance: u32) -> Point {
         go0(p, direction, distance, |next| self.is_in_bounds(next))
     }
@@ -534,6 +669,27 @@ mod test {
         Point::new(x, y)
     }
 
+    #[test]
+    fn direction_rotation() {
+        for dir in Direction::iter() {
+            let mut d = dir;
+            for _ in 0..6 {
+                d = d.rotate_cw();
+            }
+            assert_eq!(d, dir);
+            assert_eq!(dir.rotate_cw().rotate_ccw(), dir);
+            assert_eq!(dir.opposite(), dir.rotate_cw().rotate_cw().rotate_cw());
+            assert_eq!(dir.opposite().opposite(), dir);
+
+            assert_eq!(dir.angle_to(dir), 0);
+            assert_eq!(dir.angle_to(dir.rotate_cw()), 1);
+            assert_eq!(dir.angle_to(dir.rotate_ccw().rotate_ccw()), -2);
+            assert_eq!(dir.angle_to(dir.opposite()), 3);
+        }
+        assert_eq!(Direction::NE.opposite(), Direction::SW);
+        assert_eq!(Direction::NW.angle_to(Direction::E), 2);
+    }
+
     #[test]
     fn tile_hit_test_() {
         let expected = [
@@ -585,12 +741,57 @@ mod test {
         }
     }
 
+    #[test]
+    fn from_screen_edges() {
+        for &(inp, exp) in &[
+            // Vertical edge between (0, 0) and (1, 0).
+            ((31, 8), (0, 0)),
+            ((32, 8), (1, 0)),
+            // Top right diagonal edge of (0, 0) shared with (1, -1).
+            ((28, 2), (1, -1)),
+            ((28, 4), (0, 0)),
+            // Bottom left diagonal edge of (0, 0) shared with (-1, 0).
+            ((4, 12), (0, 0)),
+            ((4, 14), (-1, 0)),
+        ] {
+            assert_eq!(from_screen(inp.into()), exp.into(), "{:?}", inp);
+        }
+    }
+
+    #[test]
+    fn from_screen_round_trip() {
+        for y in -10..10 {
+            for x in -10..10 {
+                let p = P(x, y);
+                assert_eq!(from_screen(center_to_screen(p)), p);
+                assert_eq!(from_screen(to_screen(p) + P(TILE_WIDTH / 2, 1)), p);
+            }
+        }
+    }
+
     #[test]
     fn to_screen_() {
         assert_eq!(to_screen(P(0, 0)), P(0, 0));
         assert_eq!(to_screen(P(97, 63)), P(3344, 180));
     }
 
+    #[test]
+    fn center_to_screen_() {
+        // Moving along the x axis screen y alternates between staying the same (even to odd)
+        // and going up by 12 (odd to even).
+        for &(inp, exp) in &[
+            ((0, 0), (16, 8)),
+            ((0, 1), (32, 20)),
+            ((1, 0), (48, 8)),
+            ((1, 1), (64, 20)),
+            ((2, 0), (64, -4)),
+            ((3, 0), (96, -4)),
+            ((-1, 0), (0, 20)),
+        ] {
+            assert_eq!(center_to_screen(inp.into()), exp.into(), "{:?}", inp);
+        }
+    }
+
     #[test]
     fn view_from_screen2() {
         let mut t = View::default();
@@ -628,6 +829,55 @@ mod test {
         assert_eq!(go(P(22, 11), Direction::E, 1), P(23, 11));
     }
 
+    #[test]
+    fn neighbors() {
+        let t = TileGrid::default();
+        assert_eq!(t.neighbors(P(100, 100)), [
+            Some(P(101, 99)),
+            Some(P(101, 100)),
+            Some(P(100, 101)),
+            Some(P(99, 100)),
+            Some(P(99, 99)),
+            Some(P(100, 99)),
+        ]);
+        assert_eq!(t.neighbors(P(101, 100)), [
+            Some(P(102, 100)),
+            Some(P(102, 101)),
+            Some(P(101, 101)),
+            Some(P(100, 101)),
+            Some(P(100, 100)),
+            Some(P(101, 99)),
+        ]);
+        assert_eq!(t.neighbors(P(0, 100)), [
+            Some(P(1, 99)),
+            Some(P(1, 100)),
+            Some(P(0, 101)),
+            None,
+            None,
+            Some(P(0, 99)),
+        ]);
+        assert_eq!(t.neighbors(P(0, 0)), [
+            None,
+            Some(P(1, 0)),
+            Some(P(0, 1)),
+            None,
+            None,
+            None,
+        ]);
+        for (dir, n) in Direction::iter().zip(t.neighbors(P(57, 33)).iter()) {
+            assert_eq!(n.unwrap(), go(P(57, 33), dir, 1));
+        }
+    }
+
+    #[test]
+    fn arc_() {
+        assert_eq!(arc(P(100, 100), Direction::E, 0), vec![P(101, 100)]);
+        assert_eq!(arc(P(100, 100), Direction::E, 1), vec![P(101, 100), P(100, 101), P(101, 99)]);
+        assert_eq!(arc(P(101, 100), Direction::NE, 1), vec![P(102, 100), P(102, 101), P(101, 99)]);
+        assert_eq!(arc(P(100, 100), Direction::W, 3).len(), 6);
+        assert_eq!(arc(P(100, 100), Direction::W, 100), arc(P(100, 100), Direction::W, 3));
+    }
+
     #[test]
     fn direction_() {
         for dir in Direction::iter() {
@@ -636,9 +886,13 @@ mod test {
                 let to = go(from.into(), dir, dist);
                 assert_eq!(direction(from.into(), to), dir);
             }
+            // Neighbors of odd column tiles have different offsets.
+            let from = P(101, 100);
+            assert_eq!(direction(from, go(from, dir, 1)), dir);
         }
 
         assert_eq!(direction(P(98, 105), P(111, 92)), Direction::NE);
+        assert_eq!(direction(P(100, 100), P(90, 120)), Direction::SE);
     }
 
     #[test]
@@ -652,6 +906,41 @@ mod test {
         assert_eq!(distance(P(70, 102), P(92, 143)), 52);
     }
 
+    #[test]
+    fn distance_matches_neighbor_walk() {
+        use std::collections::{HashMap, VecDeque};
+
+        let t = TileGrid::default();
+        let start = P(100, 100);
+        let mut hops = HashMap::new();
+        hops.insert(start, 0);
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+        while let Some(p) = queue.pop_front() {
+            let h = hops[&p];
+            if h == 12 {
+                continue;
+            }
+            for &n in t.neighbors(p).iter() {
+                let n = n.unwrap();
+                if !hops.contains_key(&n) {
+                    hops.insert(n, h + 1);
+                    queue.push_back(n);
+                }
+            }
+        }
+        for (&p, &h) in &hops {
+            assert_eq!(distance(start, p), h, "{:?}", p);
+        }
+
+        // Same row.
+        assert_eq!(hops[&P(107, 100)], 7);
+        // Same column.
+        assert_eq!(hops[&P(100, 95)], 5);
+        // Diagonal.
+        assert_eq!(hops[&P(106, 97)], 6);
+    }
+
     #[test]
     fn try_distance_() {
         assert_eq!(try_distance(P(111, 92), P(98, 105), 19), Some(19));
@@ -680,6 +969,83 @@ mod test {
         assert_eq!(is_to_right_of(P(101, 101), P(100, 100)), false);
     }
 
+    #[test]
+    fn ring_and_spiral() {
+        use std::collections::HashSet;
+
+        let center = P(51, 50);
+        assert_eq!(ring(center, 0).collect::<Vec<_>>(), vec![center]);
+        assert_eq!(ring(center, 1).collect::<Vec<_>>(),
+            Direction::iter().map(|d| go(center, d, 1)).collect::<Vec<_>>());
+
+        let mut spiral_exp = HashSet::new();
+        for radius in 0..5 {
+            let r: Vec<_> = ring(center, radius).collect();
+            assert_eq!(r.len(), cmp::max(radius as usize * 6, 1));
+            let set: HashSet<_> = r.iter().cloned().collect();
+            assert_eq!(set.len(), r.len());
+            assert!(r.iter().all(|&p| distance(center, p) == radius));
+            spiral_exp.extend(set);
+
+            let s: Vec<_> = spiral(center, radius).collect();
+            assert_eq!(s.len(), spiral_exp.len());
+            assert_eq!(s.into_iter().collect::<HashSet<_>>(), spiral_exp);
+        }
+
+        let tg = TileGrid::default();
+        assert_eq!(tg.ring(P(0, 0), 1).collect::<Vec<_>>(), vec![P(1, 0), P(0, 1)]);
+        assert_eq!(tg.spiral(P(0, 0), 1).count(), 3);
+        assert_eq!(tg.spiral(P(100, 100), 2).count(), 19);
+    }
+
+    #[test]
+    fn knockback_tile_() {
+        let from = P(100, 100);
+        let target = go(from, Direction::E, 1);
+        assert_eq!(knockback_tile(target, from, 3, |_| false), go(target, Direction::E, 3));
+
+        let wall = go(target, Direction::E, 2);
+        assert_eq!(knockback_tile(target, from, 3, |p| p == wall), go(target, Direction::E, 1));
+
+        assert_eq!(knockback_tile(target, from, 0, |_| false), target);
+        assert_eq!(knockback_tile(target, target, 3, |_| false), target);
+    }
+
+    #[test]
+    fn tile_to_point_() {
+        assert_eq!(tile_to_point(0), P(199, 0));
+        assert_eq!(tile_to_point(199), P(0, 0));
+        assert_eq!(tile_to_point(20100), P(99, 100));
+        assert_eq!(tile_to_point(39999), P(0, 199));
+
+        for &t in &[0, 1, 199, 200, 12345, 20100, 39999] {
+            assert_eq!(point_to_tile(tile_to_point(t)), Some(t));
+        }
+
+        assert_eq!(point_to_tile(P(-1, 0)), None);
+        assert_eq!(point_to_tile(P(200, 0)), None);
+        assert_eq!(point_to_tile(P(0, 200)), None);
+    }
+
+    #[test]
+    fn line_of_sight_() {
+        let from = P(100, 100);
+        let to = P(106, 97);
+        let line: Vec<_> = ray(from, to).take_while(|&p| p != to).collect();
+        assert!(line.len() > 2);
+
+        assert!(line_of_sight(from, to, |_| false));
+        assert!(line_of_sight(from, to, |p| p == from || p == to));
+        assert!(!line_of_sight(from, to, |p| p == line[2]));
+        assert!(!line_of_sight(to, from, |p| p == line[2]));
+        assert!(line_of_sight(from, to, |p| !line.contains(&p)));
+
+        for &n in TileGrid::default().neighbors(from).iter() {
+            assert!(line_of_sight(from, n.unwrap(), |_| true));
+        }
+        assert!(line_of_sight(from, from, |_| true));
+    }
+
     #[test]
     fn beyond_() {
         let data = &[
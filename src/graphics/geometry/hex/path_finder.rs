@@ -180,6 +180,24 @@ impl PathFinder {
         None
     }
 
+    /// Finds shortest path avoiding tiles for which `blocked` returns `true`.
+    /// Returns tiles of the path including `from` and `to`. Returns `None` if there's no path or
+    /// if the search reached `max_depth` steps. See `graphics::map::find_path()` for unlimited
+    /// search.
+    pub fn find_path(&mut self, from: Point, to: Point, blocked: impl Fn(Point) -> bool)
+        -> Option<Vec<Point>>
+    {
+        let directions = self.find(from, to, false,
+            |p| if blocked(p) { TileState::Blocked } else { TileState::Passable(0) })?;
+        let mut path = Vec::with_capacity(directions.len() + 1);
+        path.push(from);
+        for direction in directions {
+            let next = self.tile_grid.go(*path.last().unwrap(), direction, 1).unwrap();
+            path.push(next);
+        }
+        Some(path)
+    }
+
     fn open(&mut self, idx: usize) {
         let cost = self.steps[idx].total_cost();
         let insert_idx = match self.open_steps
@@ -285,6 +303,30 @@ mod test {
             Some(vec![SE, SW, SW, SE]));
     }
 
+    #[test]
+    fn find_path() {
+        let mut t = PathFinder::new(TileGrid::default(), 5000);
+        let p = |x, y| Point::new(x, y);
+
+        assert_eq!(t.find_path(p(5, 5), p(5, 5), |_| false), Some(vec![p(5, 5)]));
+        assert_eq!(t.find_path(p(0, 1), p(3, 1), |_| false),
+            Some(vec![p(0, 1), p(1, 1), p(2, 2), p(3, 1)]));
+
+        // Wall from (10, 0) to (10, 19) with a gap at (10, 20).
+        let wall = |q: Point| q.x == 10 && q.y < 20;
+        let path = t.find_path(p(5, 5), p(15, 5), wall).unwrap();
+        assert_eq!(path[0], p(5, 5));
+        assert_eq!(*path.last().unwrap(), p(15, 5));
+        assert!(path.iter().all(|&q| !wall(q)));
+        assert!(path.contains(&p(10, 20)));
+        for w in path.windows(2) {
+            assert_eq!(hex::distance(w[0], w[1]), 1);
+        }
+
+        assert_eq!(t.find_path(p(5, 5), p(15, 5), |q| q == p(15, 5)), None);
+        assert_eq!(t.find_path(p(5, 5), p(15, 5), |q| q.x == 10), None);
+    }
+
     #[test]
     fn max_depth() {
         let mut t = PathFinder::new(TileGrid::default(), 10);
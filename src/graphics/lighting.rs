@@ -1,2 +1,204 @@
 pub mod light_grid;
-pub mod light_map;
\ No newline at end of file
+pub mod light_map;
+
+use std::cmp;
+use std::collections::HashMap;
+
+use crate::graphics::Point;
+use crate::graphics::geometry::TileGridView;
+use crate::graphics::geometry::camera::Camera;
+use crate::graphics::geometry::hex;
+use light_grid::{LightGrid, MAX_INTENSITY};
+
+/// Ambient light level at night.
+pub const NIGHT_LIGHT_LEVEL: u32 = 40;
+
+/// Ambient light level at day.
+pub const DAY_LIGHT_LEVEL: u32 = 100;
+
+/// Dawn starts at 6:00 and lasts one hour.
+pub const DAWN_START_MINUTES: u32 = 6 * 60;
+
+/// Dusk starts at 18:00 and lasts one hour.
+pub const DUSK_START_MINUTES: u32 = 18 * 60;
+
+/// Converts light level in range [0..100] as used by `set_light_level()` script procedure
+/// to light intensity.
+pub fn light_level_to_intensity(level: u32) -> u32 {
+    assert!(level <= 100);
+
+    const MIN: u32 = 0x4000;
+    const MID: u32 = 0xA000;
+    const MAX: u32 = 0x10000;
+
+    // TODO This probably should be fixed as follows:
+    // if v < 50 { MIN + v * (MID - MIN) / 50 } else { MID + (v - 50) * (MAX - MID) / 50 }
+    match level {
+        0..=49 => MIN + level * (MID - MIN) / 100,
+        50 => MID,
+        _ => MID + level * (MAX - MID) / 100,
+    }
+}
+
+/// Returns ambient light intensity for the time of day. This implements the `Lighting` macro
+/// from the original scripts which maps are using to update ambient light level.
+/// During dawn the light level goes from `NIGHT_LIGHT_LEVEL` up by one with each minute,
+/// during dusk it goes from `DAY_LIGHT_LEVEL` down by one with each minute.
+pub fn ambient_light(game_time_minutes: u32) -> i32 {
+    let minutes = game_time_minutes % (24 * 60);
+    let level = if (DAWN_START_MINUTES..DAWN_START_MINUTES + 60).contains(&minutes) {
+        NIGHT_LIGHT_LEVEL + minutes - DAWN_START_MINUTES
+    } else if (DAWN_START_MINUTES + 60..DUSK_START_MINUTES).contains(&minutes) {
+        DAY_LIGHT_LEVEL
+    } else if (DUSK_START_MINUTES..DUSK_START_MINUTES + 60).contains(&minutes) {
+        DAY_LIGHT_LEVEL - (minutes - DUSK_START_MINUTES)
+    } else {
+        NIGHT_LIGHT_LEVEL
+    };
+    light_level_to_intensity(level) as i32
+}
+
+/// Renders light levels of the `elevation` in `grid` as seen through the `camera`.
+/// Returns RGBA pixels of the camera viewport size, row by row. Each pixel is gray of brightness
+/// proportional to the light level of the tile under it. Pixels outside of the grid are black.
+pub fn grid_to_image(grid: &LightGrid, elevation: u32, camera: &Camera) -> Vec<u8> {
+    let hex = camera.hex();
+    let viewport = camera.viewport;
+    let mut r = Vec::with_capacity((viewport.width() * viewport.height() * 4) as usize);
+    for y in viewport.top..viewport.bottom {
+        for x in viewport.left..viewport.right {
+            let p = hex.from_screen(Point::new(x, y));
+            let v = if p.x >= 0 && p.x < grid.width() && p.y >= 0 && p.y < grid.height() {
+                (grid.get_clipped(p.elevated(elevation)) * 255 / MAX_INTENSITY) as u8
+            } else {
+                0
+            };
+            r.extend_from_slice(&[v, v, v, 0xff]);
+        }
+    }
+    r
+}
+
+/// Computes light levels of tiles lit by `sources` given as `(position, radius, intensity)`.
+/// Light intensity falls off linearly with the hex distance reaching zero at the `radius`.
+/// Tiles hidden from the source by tiles for which `blocks_light` returns `true` get no light
+/// from that source. Light of multiple sources adds up. Only tiles with non-zero light level
+/// are returned.
+pub fn propagate(sources: &[(Point, i32, i32)], blocks_light: impl Fn(Point) -> bool)
+    -> HashMap<Point, i32>
+{
+    let mut r = HashMap::new();
+    for &(pos, radius, intensity) in sources {
+        if radius <= 0 {
+            *r.entry(pos).or_insert(0) += intensity;
+            continue;
+        }
+        for y in pos.y - radius..=pos.y + radius {
+            for x in pos.x - radius..=pos.x + radius {
+                let p = Point::new(x, y);
+                let distance = hex::distance(pos, p) as i32;
+                if distance >= radius || !hex::line_of_sight(pos, p, &blocks_light) {
+                    continue;
+                }
+                *r.entry(p).or_insert(0) += intensity * (radius - distance) / radius;
+            }
+        }
+    }
+    r.retain(|_, v| *v != 0);
+    r
+}
+
+/// Returns light level of the `tile` given the `ambient` light level and `point_lights` levels
+/// as returned by `propagate()`. The brighter of the ambient and point light wins, the result
+/// doesn't exceed `MAX_INTENSITY`.
+// light_get_tile()
+pub fn final_light(ambient: i32, point_lights: &HashMap<Point, i32>, tile: Point) -> i32 {
+    let point_light = point_lights.get(&tile).copied().unwrap_or(0);
+    cmp::min(cmp::max(ambient, point_light), MAX_INTENSITY as i32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graphics::Rect;
+    use light_grid::LightTestResult;
+
+    #[test]
+    fn ambient_light_() {
+        let night = light_level_to_intensity(NIGHT_LIGHT_LEVEL) as i32;
+        assert_eq!(night, 0x6666);
+        assert_eq!(ambient_light(0), night);
+        assert_eq!(ambient_light(24 * 60), night);
+        assert_eq!(ambient_light(5 * 60 + 59), night);
+
+        assert_eq!(ambient_light(6 * 60), night);
+        assert_eq!(ambient_light(6 * 60 + 30), light_level_to_intensity(70) as i32);
+        assert_eq!(ambient_light(6 * 60 + 30), 0xe333);
+
+        assert_eq!(ambient_light(12 * 60), 0x10000);
+        assert_eq!(ambient_light(3 * 24 * 60 + 12 * 60), 0x10000);
+
+        assert_eq!(ambient_light(18 * 60), 0x10000);
+        assert_eq!(ambient_light(18 * 60 + 59), light_level_to_intensity(41) as i32);
+        assert_eq!(ambient_light(19 * 60), night);
+    }
+
+    #[test]
+    fn final_light_() {
+        let mut lights = HashMap::new();
+        lights.insert(Point::new(1, 1), 0x8000);
+        lights.insert(Point::new(2, 2), 0x20000);
+
+        assert_eq!(final_light(0x6666, &lights, Point::new(0, 0)), 0x6666);
+        assert_eq!(final_light(0x6666, &lights, Point::new(1, 1)), 0x8000);
+        assert_eq!(final_light(0x10000, &lights, Point::new(1, 1)), 0x10000);
+        assert_eq!(final_light(0x6666, &lights, Point::new(2, 2)), MAX_INTENSITY as i32);
+        assert_eq!(final_light(0x20000, &lights, Point::new(0, 0)), MAX_INTENSITY as i32);
+    }
+
+    #[test]
+    fn propagate_() {
+        use hex::Direction;
+
+        let src = Point::new(100, 100);
+        let lights = propagate(&[(src, 4, 400)], |_| false);
+        assert_eq!(lights.len(), 1 + 6 + 12 + 18);
+        for (&p, &v) in &lights {
+            assert_eq!(v, [400, 300, 200, 100][hex::distance(src, p) as usize]);
+        }
+
+        let wall = hex::go(src, Direction::E, 1);
+        let behind_wall = hex::go(src, Direction::E, 2);
+        let lights = propagate(&[(src, 4, 400)], |p| p == wall);
+        assert_eq!(lights[&wall], 300);
+        assert!(!lights.contains_key(&behind_wall));
+        assert_eq!(lights[&hex::go(src, Direction::W, 2)], 200);
+
+        let lights = propagate(&[(src, 4, 400), (behind_wall, 2, 100)], |p| p == wall);
+        assert_eq!(lights[&behind_wall], 100);
+        assert_eq!(lights[&wall], 350);
+    }
+
+    #[test]
+    fn grid_to_image_() {
+        let mut grid = LightGrid::new(20, 20, 1);
+        grid.update(Point::new(10, 10).elevated(0), 2, MAX_INTENSITY as i32,
+            |_| LightTestResult::default());
+
+        let mut camera = Camera {
+            origin: Point::new(0, 0),
+            viewport: Rect::with_size(0, 0, 320, 160),
+        };
+        camera.look_at(Point::new(10, 10));
+
+        let img = grid_to_image(&grid, 0, &camera);
+        assert_eq!(img.len(), 320 * 160 * 4);
+
+        let pixel = |p: Point| &img[((p.y * 320 + p.x) * 4) as usize..][..4];
+        let lit = pixel(camera.viewport.center());
+        let ambient = pixel(Point::new(0, 0));
+        assert_eq!(lit, &[0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(ambient[3], 0xff);
+        assert!(lit[0] > ambient[0]);
+    }
+}
@@ -8,8 +8,8 @@ use crate::util::{EnumExt, VecExt};
 const MAX_EMITTER_RADIUS: u32 = 8;
 /// Number of points inside the light cone of MAX_EMITTER_RADIUS.
 const LIGHT_CONE_LEN: usize = 36;
-const DEFAULT_LIGHT_INTENSITY: i32 = 655;
-const MAX_INTENSITY: u32 = 0x10000;
+pub const DEFAULT_LIGHT_INTENSITY: i32 = 655;
+pub const MAX_INTENSITY: u32 = 0x10000;
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct LightTest {
@@ -61,6 +61,14 @@ impl LightGrid {
         }
     }
 
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.grid[0].len() as i32 / self.width
+    }
+
     pub fn clear(&mut self) {
         for g in self.grid.iter_mut() {
             for p in g.iter_mut() {
@@ -75,7 +83,7 @@ impl LightGrid {
 
         assert!((p.elevation as usize) < self.grid.len());
         assert!(p.point.x >= 0 && p.point.x < self.width);
-        assert!(p.point.y >= 0 && p.point.y < self.grid[0].len() as i32 / self.width);
+        assert!(p.point.y >= 0 && p.point.y < self.height());
 
         if delta == 0 {
             return;
@@ -1,10 +1,20 @@
+use std::cmp::{self, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+
 use crate::graphics::geometry::{hex, TileGridView};
+use crate::graphics::geometry::hex::{Direction, TileGrid};
+use crate::graphics::geometry::hex::path_finder::PathFinder;
 use crate::graphics::lighting::light_map::{VERTEX_COUNT, VERTEX_HEXES};
-use crate::graphics::{Point, Rect};
+use crate::graphics::{EPoint, Point, Rect};
 use crate::graphics::render::{Canvas, TextureHandle};
+use crate::util::EnumExt;
 
 const ROOF_HEIGHT: i32 = 96;
 
+/// How far below the viewport tiles are kept by `cull_tiles()` so tall sprites standing on the
+/// tiles outside of the viewport are still drawn.
+const TALL_SPRITE_MARGIN: i32 = 128;
+
 pub fn render_floor(canvas: &mut dyn Canvas, stg: &impl TileGridView, rect: Rect,
         get_tex: impl FnMut(Point) -> Option<TextureHandle>,
         get_light: impl Fn(Point) -> u32) {
@@ -41,6 +51,232 @@ fn render_square_tiles(canvas: &mut dyn Canvas, stg: &impl TileGridView, rect: R
     }
 }
 
+/// Returns hex tiles within `tile_region` with their screen positions relative to `origin`.
+/// The tiles are in back-to-front order: by screen `y` and then by screen `x`.
+pub fn visible_tiles(tile_region: Rect, origin: Point) -> impl Iterator<Item=(Point, Point)> {
+    let mut r = Vec::with_capacity(cmp::max(tile_region.width() * tile_region.height(), 0) as usize);
+    for y in tile_region.top..tile_region.bottom {
+        for x in tile_region.left..tile_region.right {
+            let p = Point::new(x, y);
+            r.push((p, hex::to_screen(p) + origin));
+        }
+    }
+    r.sort_by_key(|&(_, sp)| (sp.y, sp.x));
+    r.into_iter()
+}
+
+/// Returns hex tiles on the `elevation` that need to be drawn into the screen `viewport` when the
+/// top left tile is at the `origin` on screen. Tiles below and to the sides of the viewport are
+/// included too since sprites standing on them can extend into the viewport. The tiles are in
+/// back-to-front order. Tiles outside of the `tile_grid` are skipped.
+pub fn cull_tiles(tile_grid: &TileGrid, viewport: Rect, origin: Point, elevation: u32)
+    -> Vec<EPoint>
+{
+    if viewport.left >= viewport.right || viewport.top >= viewport.bottom {
+        return Vec::new();
+    }
+    let region = Rect::new(
+        viewport.left - hex::TILE_WIDTH,
+        viewport.top,
+        viewport.right + hex::TILE_WIDTH,
+        viewport.bottom + TALL_SPRITE_MARGIN);
+    let tile_region = hex::from_screen_rect(region.translate(-origin));
+    visible_tiles(tile_region, origin)
+        .map(|(p, _)| p)
+        .filter(|&p| tile_grid.is_in_bounds(p))
+        .map(|p| p.elevated(elevation))
+        .collect()
+}
+
+/// Returns edges of the hexes whose screen bounds intersect the screen `region`. Edges
+/// shared by adjacent hexes are returned once.
+pub fn hex_grid_lines(region: Rect) -> impl Iterator<Item=(Point, Point)> {
+    // Hex vertices relative to the tile screen position in clockwise order.
+    const VERTICES: [Point; 6] = [
+        Point::new(0, hex::TILE_INNER_HEIGHT / 2),
+        Point::new(hex::TILE_WIDTH / 2, 0),
+        Point::new(hex::TILE_WIDTH, hex::TILE_INNER_HEIGHT / 2),
+        Point::new(hex::TILE_WIDTH, hex::TILE_HEIGHT - hex::TILE_INNER_HEIGHT / 2),
+        Point::new(hex::TILE_WIDTH / 2, hex::TILE_HEIGHT),
+        Point::new(0, hex::TILE_HEIGHT - hex::TILE_INNER_HEIGHT / 2),
+    ];
+
+    let mut r = Vec::new();
+    if !region.is_empty() {
+        let tiles = hex::from_screen_rect(region);
+        for y in tiles.top - 1..tiles.bottom + 1 {
+            for x in tiles.left - 1..tiles.right + 1 {
+                let sp = hex::to_screen(Point::new(x, y));
+                let bounds = Rect::from_point_size(sp, (hex::TILE_WIDTH, hex::TILE_HEIGHT));
+                if !bounds.intersects(region) {
+                    continue;
+                }
+                for i in 0..VERTICES.len() {
+                    let a = (sp + VERTICES[i]).tuple();
+                    let b = (sp + VERTICES[(i + 1) % VERTICES.len()]).tuple();
+                    r.push(if a <= b { (a, b) } else { (b, a) });
+                }
+            }
+        }
+    }
+    r.sort();
+    r.dedup();
+    r.into_iter().map(|(a, b)| (a.into(), b.into()))
+}
+
+/// Returns hex tiles reachable from `from` with the cheapest total cost to reach each of them.
+/// `cost` returns cost of entering the tile or `None` if the tile is impassable. Tiles with total
+/// cost greater than `max_cost` are not reachable. The `from` tile is reachable with cost 0.
+pub fn reachable(from: Point, max_cost: i32, cost: impl Fn(Point) -> Option<i32>)
+    -> HashMap<Point, i32>
+{
+    let mut r = HashMap::new();
+    if max_cost < 0 {
+        return r;
+    }
+    r.insert(from, 0);
+    let mut queue = BinaryHeap::new();
+    queue.push((Reverse(0), from.tuple()));
+    while let Some((Reverse(total), p)) = queue.pop() {
+        let p = Point::from(p);
+        if total > r[&p] {
+            continue;
+        }
+        for dir in Direction::iter() {
+            let next = hex::go(p, dir, 1);
+            let next_total = if let Some(c) = cost(next) {
+                total + c
+            } else {
+                continue;
+            };
+            if next_total > max_cost || r.get(&next).map(|&t| t <= next_total).unwrap_or(false) {
+                continue;
+            }
+            r.insert(next, next_total);
+            queue.push((Reverse(next_total), next.tuple()));
+        }
+    }
+    r
+}
+
+/// Finds shortest path on the default tile grid avoiding tiles for which `blocked` returns `true`.
+/// Returns tiles of the path including `from` and `to`, or `None` if there's no path. Unlike
+/// `PathFinder::find_path()` the search depth is not limited.
+pub fn find_path(from: Point, to: Point, blocked: impl Fn(Point) -> bool) -> Option<Vec<Point>> {
+    let tile_grid = TileGrid::default();
+    // Every tile is visited at most once so the search can't reach this depth.
+    let max_depth = tile_grid.len() + 1;
+    PathFinder::new(tile_grid, max_depth).find_path(from, to, blocked)
+}
+
+/// Returns hex tiles of the cone starting next to the `origin` and going `length` tiles in the
+/// `direction`. The cone widens linearly up to `half_width` tiles on each side at the far end
+/// but not wider than 60 degrees on each side. Tiles are returned row by row from the origin
+/// outwards, each row from its center to the flanks. Tiles outside of the `tile_grid` are skipped.
+pub fn cone(tile_grid: &TileGrid, origin: Point, direction: Direction, length: u32,
+    half_width: u32) -> impl Iterator<Item=Point>
+{
+    // Going in these directions from the center of the row keeps the distance from the origin.
+    let flanks = [direction.rotate_ccw().rotate_ccw(), direction.rotate_cw().rotate_cw()];
+    let mut r = Vec::new();
+    for d in 1..=length {
+        let center = hex::go(origin, direction, d);
+        r.push(center);
+        let width = cmp::min(half_width * d / length, d);
+        for k in 1..=width {
+            for &flank in &flanks {
+                r.push(hex::go(center, flank, k));
+            }
+        }
+    }
+    let tile_grid = tile_grid.clone();
+    r.into_iter().filter(move |&p| tile_grid.is_in_bounds(p))
+}
+
+/// Objects bucketed by hex tile for fast region queries.
+#[derive(Clone, Debug)]
+pub struct SpatialIndex<T> {
+    tiles: HashMap<EPoint, Vec<T>>,
+    len: usize,
+}
+
+impl<T> SpatialIndex<T> {
+    pub fn new() -> Self {
+        Self {
+            tiles: HashMap::new(),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn insert(&mut self, pos: EPoint, value: T) {
+        self.tiles.entry(pos).or_default().push(value);
+        self.len += 1;
+    }
+
+    /// Removes the first object at `pos` equal to `value`. Returns `false` if there's no such
+    /// object.
+    pub fn remove(&mut self, pos: EPoint, value: &T) -> bool
+        where T: PartialEq
+    {
+        let objs = if let Some(v) = self.tiles.get_mut(&pos) {
+            v
+        } else {
+            return false;
+        };
+        let i = if let Some(i) = objs.iter().position(|v| v == value) {
+            i
+        } else {
+            return false;
+        };
+        objs.remove(i);
+        if objs.is_empty() {
+            self.tiles.remove(&pos);
+        }
+        self.len -= 1;
+        true
+    }
+
+    /// Returns objects at `pos`.
+    pub fn at(&self, pos: EPoint) -> &[T] {
+        self.tiles.get(&pos).map(|v| &v[..]).unwrap_or(&[])
+    }
+
+    /// Returns objects on the `elevation` whose tile center is within the screen `region`.
+    pub fn query_region<'a>(&'a self, elevation: u32, region: Rect, view: &impl TileGridView)
+        -> impl Iterator<Item=&'a T> + 'a
+    {
+        let mut tiles = Vec::new();
+        if region.left < region.right && region.top < region.bottom {
+            let tile_rect = view.from_screen_rect(region);
+            for y in tile_rect.top..tile_rect.bottom {
+                for x in tile_rect.left..tile_rect.right {
+                    let pos = Point::new(x, y).elevated(elevation);
+                    if self.tiles.contains_key(&pos)
+                        && region.contains(view.center_to_screen(pos.point))
+                    {
+                        tiles.push(pos);
+                    }
+                }
+            }
+        }
+        tiles.into_iter().flat_map(move |pos| self.tiles[&pos].iter())
+    }
+}
+
+impl<T> Default for SpatialIndex<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // Whether scroll is restricted based on horz/vert distance from `dude_pos` to the new `pos`.
 pub fn is_scroll_limited(pos: Point, dude_pos: Point) -> bool {
     let dist = hex::to_screen(dude_pos) - hex::to_screen(pos);
@@ -69,3 +305,188 @@ pub fn is_scroll_limited(pos: Point, dude_pos: Point) -> bool {
 
 
 
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn visible_tiles_() {
+        let origin = Point::new(100, 50);
+        let tiles: Vec<_> = visible_tiles(Rect::with_size(10, 20, 3, 2), origin).collect();
+        assert_eq!(tiles.len(), 6);
+        for &(p, sp) in &tiles {
+            assert_eq!(sp, hex::to_screen(p) + origin);
+        }
+        assert!(tiles.windows(2).all(|w| (w[0].1.y, w[0].1.x) <= (w[1].1.y, w[1].1.x)));
+        assert_eq!(tiles[0], (Point::new(12, 20), hex::to_screen(Point::new(12, 20)) + origin));
+        assert_eq!(tiles[5].0, Point::new(11, 21));
+
+        assert_eq!(visible_tiles(Rect::empty(), origin).count(), 0);
+    }
+
+    #[test]
+    fn cull_tiles_() {
+        let tg = TileGrid::default();
+        let origin = -hex::to_screen(Point::new(100, 100));
+        let small = Rect::with_size(0, 0, 320, 240);
+        let big = Rect::with_size(0, 0, 640, 480);
+
+        let tiles = cull_tiles(&tg, small, origin, 1);
+        assert!(tiles.iter().all(|p| p.elevation == 1));
+        let big_count = cull_tiles(&tg, big, origin, 1).len();
+        assert!(big_count > tiles.len() * 2, "{} {}", big_count, tiles.len());
+
+        for &corner in &[
+            Point::new(small.left, small.top),
+            Point::new(small.right - 1, small.top),
+            Point::new(small.left, small.bottom - 1),
+            Point::new(small.right - 1, small.bottom - 1),
+        ] {
+            let p = hex::from_screen(corner - origin).elevated(1);
+            assert!(tiles.contains(&p), "{:?}", p);
+        }
+
+        let screen_pos: Vec<_> = tiles.iter().map(|p| hex::to_screen(p.point)).collect();
+        assert!(screen_pos.windows(2).all(|w| (w[0].y, w[0].x) <= (w[1].y, w[1].x)));
+
+        assert!(cull_tiles(&tg, Rect::empty(), origin, 0).is_empty());
+        assert!(cull_tiles(&tg, small, Point::new(0, 0), 0).iter()
+            .all(|p| tg.is_in_bounds(p.point)));
+    }
+
+    #[test]
+    fn hex_grid_lines_() {
+        let sp = hex::to_screen(Point::new(10, 20));
+
+        let lines: Vec<_> = hex_grid_lines(Rect::with_size(sp.x + 8, sp.y + 6, 4, 4)).collect();
+        assert_eq!(lines.len(), 6);
+        assert!(lines.contains(&(sp + Point::new(0, 4), sp + Point::new(16, 0))));
+
+        // Two hexes sharing an edge.
+        let lines: Vec<_> = hex_grid_lines(Rect::with_size(sp.x + 28, sp.y + 6, 8, 4)).collect();
+        assert_eq!(lines.len(), 11);
+
+        let lines: Vec<_> = hex_grid_lines(Rect::with_size(sp.x, sp.y, 200, 100)).collect();
+        for (i, l) in lines.iter().enumerate() {
+            assert!(!lines[i + 1..].contains(l));
+            assert!(!lines.contains(&(l.1, l.0)));
+        }
+
+        assert_eq!(hex_grid_lines(Rect::empty()).count(), 0);
+    }
+
+    #[test]
+    fn cone_() {
+        let tg = TileGrid::default();
+        let origin = Point::new(100, 100);
+
+        let line: Vec<_> = cone(&tg, origin, Direction::SE, 4, 0).collect();
+        assert_eq!(line, (1..=4).map(|d| hex::go(origin, Direction::SE, d)).collect::<Vec<_>>());
+
+        let tiles: Vec<_> = cone(&tg, origin, Direction::E, 4, 2).collect();
+        assert_eq!(tiles.len(), 4 + 2 * (1 + 1 + 2));
+        for &p in &tiles {
+            assert_eq!(hex::direction(origin, p), Direction::E);
+        }
+        let c2 = hex::go(origin, Direction::E, 2);
+        assert_eq!(&tiles[1..4],
+            &[c2, hex::go(c2, Direction::NW, 1), hex::go(c2, Direction::SW, 1)]);
+        let c4 = hex::go(origin, Direction::E, 4);
+        assert!(tiles.contains(&hex::go(c4, Direction::NW, 2)));
+        assert!(tiles.contains(&hex::go(c4, Direction::SW, 2)));
+        assert!(!tiles.contains(&hex::go(c4, Direction::SW, 3)));
+
+        // Can't be wider than 60 degrees.
+        assert_eq!(cone(&tg, origin, Direction::E, 2, 100).count(), 3 + 5);
+
+        assert_eq!(cone(&tg, origin, Direction::E, 0, 2).count(), 0);
+
+        let corner = Point::new(0, 0);
+        let tiles: Vec<_> = Direction::iter().flat_map(|d| cone(&tg, corner, d, 3, 3)).collect();
+        assert!(!tiles.is_empty());
+        assert!(tiles.len() < 6 * (1 + 3 + 5));
+        assert!(tiles.iter().all(|&p| tg.is_in_bounds(p)));
+    }
+
+    #[test]
+    fn reachable_() {
+        let from = Point::new(10, 10);
+        let r = reachable(from, 2, |_| Some(1));
+        assert_eq!(r.len(), 19);
+        assert_eq!(r[&from], 0);
+        for (&p, &c) in &r {
+            assert_eq!(c, hex::distance(from, p) as i32);
+        }
+        assert!(!r.contains_key(&hex::go(from, Direction::E, 3)));
+
+        // Wall to the east of `from` is routed around.
+        let wall = hex::go(from, Direction::E, 1);
+        let r = reachable(from, 3, |p| if p == wall { None } else { Some(1) });
+        assert!(!r.contains_key(&wall));
+        assert_eq!(r[&hex::go(from, Direction::E, 2)], 3);
+
+        assert!(r.values().all(|&c| c <= 3));
+        assert!(reachable(from, -1, |_| Some(1)).is_empty());
+    }
+
+    #[test]
+    fn find_path_() {
+        let p = |x, y| Point::new(x, y);
+
+        assert_eq!(find_path(p(0, 1), p(3, 1), |_| false),
+            Some(vec![p(0, 1), p(1, 1), p(2, 2), p(3, 1)]));
+
+        // Wall from (10, 0) to (10, 179) with a gap at (10, 180). The detour is too deep for
+        // a depth limited `PathFinder`.
+        let wall = |q: Point| q.x == 10 && q.y < 180;
+        assert_eq!(PathFinder::new(TileGrid::default(), 5000).find_path(p(5, 5), p(15, 5), wall),
+            None);
+        let path = find_path(p(5, 5), p(15, 5), wall).unwrap();
+        assert_eq!(path[0], p(5, 5));
+        assert_eq!(*path.last().unwrap(), p(15, 5));
+        assert!(path.iter().all(|&q| !wall(q)));
+        assert!(path.contains(&p(10, 180)));
+        for w in path.windows(2) {
+            assert_eq!(hex::distance(w[0], w[1]), 1);
+        }
+
+        assert_eq!(find_path(p(5, 5), p(15, 5), |q| q == p(15, 5)), None);
+    }
+
+    #[test]
+    fn spatial_index() {
+        let view = hex::View::new(Point::new(0, 0));
+        let mut idx = SpatialIndex::new();
+        let p1 = Point::new(10, 10);
+        let p2 = Point::new(11, 10);
+        let far = Point::new(50, 50);
+        idx.insert(p1.elevated(0), 1);
+        idx.insert(p1.elevated(0), 2);
+        idx.insert(p2.elevated(0), 3);
+        idx.insert(far.elevated(0), 4);
+        idx.insert(p1.elevated(1), 5);
+        assert_eq!(idx.len(), 5);
+
+        let c1 = view.center_to_screen(p1);
+        let c2 = view.center_to_screen(p2);
+        let region = Rect::new(cmp::min(c1.x, c2.x), cmp::min(c1.y, c2.y),
+            cmp::max(c1.x, c2.x) + 1, cmp::max(c1.y, c2.y) + 1);
+        let query = |idx: &SpatialIndex<i32>, elevation| {
+            let mut r: Vec<_> = idx.query_region(elevation, region, &view).copied().collect();
+            r.sort();
+            r
+        };
+        assert_eq!(query(&idx, 0), vec![1, 2, 3]);
+        assert_eq!(query(&idx, 1), vec![5]);
+        assert_eq!(idx.query_region(0, Rect::empty(), &view).count(), 0);
+
+        assert!(idx.remove(p1.elevated(0), &2));
+        assert!(!idx.remove(p1.elevated(0), &2));
+        assert!(!idx.remove(far.elevated(1), &4));
+        assert_eq!(query(&idx, 0), vec![1, 3]);
+        assert!(idx.remove(p2.elevated(0), &3));
+        assert_eq!(query(&idx, 0), vec![1]);
+        assert_eq!(idx.at(p2.elevated(0)), &[]);
+        assert_eq!(idx.len(), 3);
+    }
+}
@@ -6,8 +6,11 @@ use std::fmt;
 use std::rc::Rc;
 use std::time::Instant;
 
-use crate::graphics::{Point, Rect};
+use crate::asset::ExactEntityKind;
+use crate::graphics::{EPoint, Point, Rect};
 use crate::graphics::color::Rgb15;
+use crate::graphics::geometry::TileGridView;
+use crate::graphics::geometry::camera::Camera;
 use crate::graphics::font::{self, FontKey, Fonts};
 
 #[derive(Clone)]
@@ -19,6 +22,14 @@ impl fmt::Debug for TextureHandle {
     }
 }
 
+impl PartialEq for TextureHandle {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.key == other.0.key
+    }
+}
+
+impl Eq for TextureHandle {}
+
 new_handle_type! {
     struct Key;
 }
@@ -68,12 +79,21 @@ pub trait Canvas {
 
     fn fonts(&self) -> &Rc<Fonts>;
 
+    fn clip_rect(&self) -> Rect;
     fn set_clip_rect(&mut self, rect: Rect);
     fn reset_clip_rect(&mut self);
 
     fn clear(&mut self, color: Rgb15);
 
+    /// Fills the `rect` with solid `color`.
+    fn fill(&mut self, rect: Rect, color: Rgb15);
+
     fn draw(&mut self, tex: &TextureHandle, pos: Point, light: u32);
+
+    /// Draws `tex` at each `(pos, light)` of `sprites` in order. Same as calling `draw()` for
+    /// each sprite but the texture is looked up only once.
+    fn draw_batch(&mut self, tex: &TextureHandle, sprites: &[(Point, u32)]);
+
     fn draw_multi_light(&mut self, tex: &TextureHandle, pos: Point, lights: &[u32]);
 
     /// Draws the specified `texture` masked using the specified `mask`.
@@ -99,4 +119,468 @@ pub trait Canvas {
     fn draw_outline(&mut self, tex: &TextureHandle, pos: Point, outline: Outline);
     fn draw_text(&mut self, text: &bstr, pos: Point, font: FontKey, color: Rgb15,
         options: &font::DrawOptions);
-}
\ No newline at end of file
+}
+
+/// Calls `f` with the `canvas` clip rect narrowed to the intersection of the current clip rect and
+/// `rect`. The previous clip rect is restored afterwards. Nested calls compose.
+pub fn with_clip(canvas: &mut dyn Canvas, rect: Rect, f: impl FnOnce(&mut dyn Canvas)) {
+    let old = canvas.clip_rect();
+    canvas.set_clip_rect(rect.intersect(old));
+    f(canvas);
+    canvas.set_clip_rect(old);
+}
+
+/// Returns indices of `items` in back-to-front draw order. Items are ordered by elevation,
+/// then by tile row, then by tile column from right to left (matching the order objects are
+/// rendered in `Objects::render()`). Items at the same position keep their relative order
+/// in `items`.
+pub fn draw_order<T>(items: &[T], pos: impl Fn(&T) -> EPoint) -> Vec<usize> {
+    let mut r: Vec<_> = (0..items.len()).collect();
+    r.sort_by_key(|&i| {
+        let EPoint { elevation, point } = pos(&items[i]);
+        (elevation, point.y, -point.x)
+    });
+    r
+}
+
+/// Accumulates sprite draws grouping consecutive sprites of the same texture into batches.
+/// Sprites are drawn in the order they were pushed, so pushing a sprite of a different texture
+/// starts a new batch even if there's an earlier batch of the same texture.
+#[derive(Default)]
+pub struct SpriteBatch {
+    batches: Vec<Batch>,
+}
+
+struct Batch {
+    texture: TextureHandle,
+    sprites: Vec<(Point, u32)>,
+}
+
+impl SpriteBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, texture: &TextureHandle, pos: Point, light: u32) {
+        match self.batches.last_mut() {
+            Some(last) if &last.texture == texture => last.sprites.push((pos, light)),
+            _ => self.batches.push(Batch {
+                texture: texture.clone(),
+                sprites: vec![(pos, light)],
+            }),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+
+    pub fn batch_count(&self) -> usize {
+        self.batches.len()
+    }
+
+    /// Draws all accumulated sprites to the `canvas` and returns the number of batches drawn.
+    pub fn flush(&mut self, canvas: &mut dyn Canvas) -> usize {
+        let r = self.batches.len();
+        for batch in self.batches.drain(..) {
+            canvas.draw_batch(&batch.texture, &batch.sprites);
+        }
+        r
+    }
+}
+
+/// Size of the blip square drawn by `draw_blips()`.
+pub const BLIP_SIZE: i32 = 2;
+
+/// Draws a small dot centered at the tile of each object in `objects`. Objects whose screen
+/// position falls outside of `clip` are skipped, as well as objects `color_by_kind()` returns
+/// `None` for.
+pub fn draw_blips(dst: &mut dyn Canvas,
+        objects: impl IntoIterator<Item=(ExactEntityKind, Point)>,
+        camera: &Camera,
+        color_by_kind: impl Fn(ExactEntityKind) -> Option<Rgb15>,
+        clip: Rect) {
+    let hex = camera.hex();
+    for (kind, pos) in objects {
+        let color = if let Some(v) = color_by_kind(kind) {
+            v
+        } else {
+            continue;
+        };
+        let pos = hex.center_to_screen(pos);
+        if !clip.contains(pos) {
+            continue;
+        }
+        let rect = Rect::with_size(pos.x - BLIP_SIZE / 2, pos.y - BLIP_SIZE / 2,
+            BLIP_SIZE, BLIP_SIZE);
+        dst.fill(rect.intersect(clip), color);
+    }
+}
+
+/// Fills the `rect` with vertical gradient going from `top` color at the top row to `bottom`
+/// color at the bottom row. Only rows within the current clip rect of the `canvas` are drawn.
+pub fn fill_gradient(canvas: &mut dyn Canvas, rect: Rect, top: Rgb15, bottom: Rgb15) {
+    let clip = canvas.clip_rect();
+    let height = rect.height();
+    for (y, span) in rect.scanlines(&clip) {
+        let color = if height > 1 {
+            let rem = (rect.bottom - 1 - y) as u32;
+            let d = (height - 1) as u32;
+            top.mix(bottom, ((rem * 255 + d / 2) / d) as u8)
+        } else {
+            top
+        };
+        canvas.fill(Rect::new(span.start, y, span.end, y + 1), color);
+    }
+}
+
+/// Returns index of the first rect in `rects` that contains the `point`.
+pub fn hit_test(rects: &[Rect], point: Point) -> Option<usize> {
+    rects.iter().position(|r| r.contains(point))
+}
+
+/// Decides what is visible when viewing the map at `elevation`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct VisibilityFilter {
+    pub elevation: u32,
+    pub show_roof: bool,
+}
+
+impl VisibilityFilter {
+    pub fn new(elevation: u32, show_roof: bool) -> Self {
+        Self {
+            elevation,
+            show_roof,
+        }
+    }
+
+    /// Only things at the current elevation are visible. Roofs are visible only if `show_roof`
+    /// is set.
+    pub fn is_visible(&self, p: EPoint, is_roof: bool) -> bool {
+        p.elevation == self.elevation && (!is_roof || self.show_roof)
+    }
+}
+
+/// Returns objects at `elevation` whose tile center in screen coordinates is within `rect`.
+/// Useful for rubber-band selection.
+pub fn select_in_rect<T>(rect: Rect, objects: impl IntoIterator<Item=(T, EPoint)>, elevation: u32,
+        view: &impl TileGridView) -> Vec<T> {
+    objects.into_iter()
+        .filter(|(_, pos)| pos.elevation == elevation &&
+            rect.contains(view.center_to_screen(pos.point)))
+        .map(|(obj, _)| obj)
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::asset::ItemKind;
+    use crate::graphics::geometry::hex;
+
+    /// Operation recorded by `CaptureRenderer`.
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    pub enum Op {
+        SetClipRect(Rect),
+        ResetClipRect,
+        Clear(Rgb15),
+        Fill(Rect, Rgb15),
+        Draw(Point),
+        DrawBatch(Vec<Point>),
+        DrawText(Point),
+    }
+
+    /// `Canvas` that records draw operations instead of rendering them.
+    /// `Op::Fill` is recorded with the rect clipped to the current clip rect.
+    pub struct CaptureRenderer {
+        pub ops: Vec<Op>,
+        clip_rect: Rect,
+        fonts: Rc<Fonts>,
+    }
+
+    impl CaptureRenderer {
+        pub fn new() -> Self {
+            Self {
+                ops: Vec::new(),
+                clip_rect: Rect::full(),
+                fonts: Rc::new(Fonts::new()),
+            }
+        }
+    }
+
+    impl Canvas for CaptureRenderer {
+        fn cleanup(&mut self) {}
+        fn present(&mut self) {}
+        fn update(&mut self, _time: Instant) {}
+
+        fn fonts(&self) -> &Rc<Fonts> {
+            &self.fonts
+        }
+
+        fn clip_rect(&self) -> Rect {
+            self.clip_rect
+        }
+
+        fn set_clip_rect(&mut self, rect: Rect) {
+            self.clip_rect = rect;
+            self.ops.push(Op::SetClipRect(rect));
+        }
+
+        fn reset_clip_rect(&mut self) {
+            self.clip_rect = Rect::full();
+            self.ops.push(Op::ResetClipRect);
+        }
+
+        fn clear(&mut self, color: Rgb15) {
+            self.ops.push(Op::Clear(color));
+        }
+
+        fn fill(&mut self, rect: Rect, color: Rgb15) {
+            let rect = rect.intersect(self.clip_rect);
+            if !rect.is_empty() {
+                self.ops.push(Op::Fill(rect, color));
+            }
+        }
+
+        fn draw(&mut self, _tex: &TextureHandle, pos: Point, _light: u32) {
+            self.ops.push(Op::Draw(pos));
+        }
+
+        fn draw_batch(&mut self, _tex: &TextureHandle, sprites: &[(Point, u32)]) {
+            self.ops.push(Op::DrawBatch(sprites.iter().map(|&(pos, _)| pos).collect()));
+        }
+
+        fn draw_multi_light(&mut self, _tex: &TextureHandle, pos: Point, _lights: &[u32]) {
+            self.ops.push(Op::Draw(pos));
+        }
+
+        fn draw_masked(&mut self, _texture: &TextureHandle, pos: Point,
+                       _mask: &TextureHandle, _mask_pos: Point,
+                       _light: u32) {
+            self.ops.push(Op::Draw(pos));
+        }
+
+        fn draw_masked_color(&mut self, _src: Rgb15, _dst: Option<Rgb15>, pos: Point,
+                             _mask: &TextureHandle) {
+            self.ops.push(Op::Draw(pos));
+        }
+
+        fn draw_highlight(&mut self, _color: Rgb15, pos: Point, _mask: &TextureHandle) {
+            self.ops.push(Op::Draw(pos));
+        }
+
+        fn draw_translucent(&mut self, _tex: &TextureHandle, pos: Point, _color: Rgb15,
+                _light: u32) {
+            self.ops.push(Op::Draw(pos));
+        }
+
+        fn draw_translucent_dark(&mut self, _tex: &TextureHandle, pos: Point, _color: Rgb15,
+                _light: u32) {
+            self.ops.push(Op::Draw(pos));
+        }
+
+        fn draw_outline(&mut self, _tex: &TextureHandle, pos: Point, _outline: Outline) {
+            self.ops.push(Op::Draw(pos));
+        }
+
+        fn draw_text(&mut self, _text: &bstr, pos: Point, _font: FontKey, _color: Rgb15,
+                _options: &font::DrawOptions) {
+            self.ops.push(Op::DrawText(pos));
+        }
+    }
+
+    #[test]
+    fn with_clip_() {
+        let mut c = CaptureRenderer::new();
+        let white = Rgb15::new(31, 31, 31);
+        let big = Rect::with_size(0, 0, 100, 100);
+        with_clip(&mut c, Rect::with_size(10, 20, 30, 40), |c| {
+            c.fill(big, white);
+            with_clip(c, Rect::with_size(0, 50, 100, 100), |c| {
+                c.fill(big, white);
+                with_clip(c, Rect::with_size(200, 200, 10, 10), |c| {
+                    c.fill(big, white);
+                });
+            });
+            c.fill(big, white);
+        });
+        c.fill(big, white);
+
+        let fills: Vec<_> = c.ops.iter()
+            .filter_map(|op| if let Op::Fill(r, _) = op { Some(*r) } else { None })
+            .collect();
+        assert_eq!(fills, vec![
+            Rect::with_size(10, 20, 30, 40),
+            Rect::with_size(10, 50, 30, 10),
+            Rect::with_size(10, 20, 30, 40),
+            big,
+        ]);
+        assert_eq!(c.clip_rect(), Rect::full());
+    }
+
+    #[test]
+    fn draw_order_() {
+        let items = [
+            (EPoint::new(1, Point::new(5, 5)), "a"),
+            (EPoint::new(0, Point::new(5, 5)), "b"),
+            (EPoint::new(0, Point::new(4, 6)), "c"),
+            (EPoint::new(0, Point::new(5, 5)), "d"),
+            (EPoint::new(0, Point::new(6, 5)), "e"),
+            (EPoint::new(0, Point::new(100, 4)), "f"),
+            (EPoint::new(0, Point::new(5, 5)), "g"),
+        ];
+        let order: Vec<_> = draw_order(&items, |&(p, _)| p).into_iter()
+            .map(|i| items[i].1)
+            .collect();
+        assert_eq!(order, vec!["f", "e", "b", "d", "g", "c", "a"]);
+    }
+
+    fn texture_handles(count: usize) -> Vec<TextureHandle> {
+        let mut keys = slotmap::SlotMap::<Key, ()>::with_key();
+        let drop_list = Rc::new(RefCell::new(Vec::new()));
+        (0..count)
+            .map(|_| TextureHandle(Rc::new(TextureHandleInner {
+                key: keys.insert(()),
+                drop_list: drop_list.clone(),
+            })))
+            .collect()
+    }
+
+    #[test]
+    fn sprite_batch() {
+        let t = texture_handles(2);
+        let p = |i| Point::new(i, 0);
+
+        let mut c = CaptureRenderer::new();
+        let mut b = SpriteBatch::new();
+        b.push(&t[0], p(0), 0);
+        b.push(&t[0], p(1), 0);
+        b.push(&t[1], p(2), 0);
+        b.push(&t[1], p(3), 0);
+        assert_eq!(b.batch_count(), 2);
+        assert_eq!(b.flush(&mut c), 2);
+        assert!(b.is_empty());
+        assert_eq!(b.flush(&mut c), 0);
+
+        b.push(&t[0], p(4), 0);
+        b.push(&t[1], p(5), 0);
+        b.push(&t[0], p(6), 0);
+        b.push(&t[1].clone(), p(7), 0);
+        assert_eq!(b.flush(&mut c), 4);
+
+        // One canvas operation per batch.
+        assert_eq!(c.ops, vec![
+            Op::DrawBatch(vec![p(0), p(1)]),
+            Op::DrawBatch(vec![p(2), p(3)]),
+            Op::DrawBatch(vec![p(4)]),
+            Op::DrawBatch(vec![p(5)]),
+            Op::DrawBatch(vec![p(6)]),
+            Op::DrawBatch(vec![p(7)]),
+        ]);
+    }
+
+    #[test]
+    fn hit_test_() {
+        let rects = [
+            Rect::with_size(10, 10, 20, 20),
+            Rect::with_size(0, 0, 100, 100),
+            Rect::with_size(15, 15, 5, 5),
+        ];
+        assert_eq!(hit_test(&rects, Point::new(16, 16)), Some(0));
+        assert_eq!(hit_test(&rects, Point::new(30, 30)), Some(1));
+        assert_eq!(hit_test(&rects, Point::new(0, 0)), Some(1));
+        assert_eq!(hit_test(&rects, Point::new(100, 50)), None);
+        assert_eq!(hit_test(&[], Point::new(0, 0)), None);
+    }
+
+    #[test]
+    fn visibility_filter() {
+        let p = Point::new(10, 10);
+        let f = VisibilityFilter::new(1, false);
+        assert!(f.is_visible(p.elevated(1), false));
+        assert!(!f.is_visible(p.elevated(0), false));
+        assert!(!f.is_visible(p.elevated(2), false));
+        assert!(!f.is_visible(p.elevated(1), true));
+
+        let f = VisibilityFilter::new(1, true);
+        assert!(f.is_visible(p.elevated(1), true));
+        assert!(!f.is_visible(p.elevated(0), true));
+    }
+
+    #[test]
+    fn select_in_rect_() {
+        let view = hex::View::new(Point::new(0, 0));
+        let p1 = Point::new(10, 10);
+        let p2 = Point::new(10, 12);
+        let sp1 = view.center_to_screen(p1);
+        let sp2 = view.center_to_screen(p2);
+        let rect = Rect::with_points(sp1, sp2);
+        let objects = [
+            // On the top left boundary.
+            (1, p1.elevated(0)),
+            // On the bottom right boundary.
+            (2, p2.elevated(0)),
+            (3, Point::new(10, 11).elevated(0)),
+            // Different elevation.
+            (4, Point::new(10, 11).elevated(1)),
+        ];
+        assert_eq!(select_in_rect(rect, objects.iter().cloned(), 0, &view), vec![1, 3]);
+        assert_eq!(select_in_rect(rect, objects.iter().cloned(), 1, &view), vec![4]);
+
+        let rect = Rect::with_points(sp1, sp2 + Point::new(1, 1));
+        assert_eq!(select_in_rect(rect, objects.iter().cloned(), 0, &view), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn draw_blips_() {
+        let mut camera = Camera {
+            origin: Point::new(0, 0),
+            viewport: Rect::with_size(0, 0, 640, 380),
+        };
+        camera.look_at(Point::new(100, 100));
+        let mut c = CaptureRenderer::new();
+        let red = Rgb15::new(31, 0, 0);
+        let objects = [
+            (ExactEntityKind::Critter, Point::new(100, 100)),
+            (ExactEntityKind::Critter, Point::new(103, 98)),
+            (ExactEntityKind::Item(ItemKind::Weapon), Point::new(100, 100)),
+            // Off-screen.
+            (ExactEntityKind::Critter, Point::new(150, 100)),
+        ];
+        let color_by_kind = |k| if k == ExactEntityKind::Critter { Some(red) } else { None };
+        draw_blips(&mut c, objects.iter().cloned(), &camera, color_by_kind, camera.viewport);
+
+        let expected: Vec<_> = objects[..2].iter()
+            .map(|&(_, p)| {
+                let p = camera.hex().center_to_screen(p);
+                Op::Fill(Rect::with_size(p.x - 1, p.y - 1, 2, 2), red)
+            })
+            .collect();
+        assert_eq!(c.ops, expected);
+    }
+
+    #[test]
+    fn fill_gradient_() {
+        let mut c = CaptureRenderer::new();
+        let top = Rgb15::new(31, 0, 10);
+        let bottom = Rgb15::new(0, 31, 10);
+        let rect = Rect::with_size(10, 20, 5, 11);
+        fill_gradient(&mut c, rect, top, bottom);
+
+        assert_eq!(c.ops.len(), 11);
+        let row = |i: usize| if let Op::Fill(r, color) = c.ops[i] {
+            assert_eq!(r, Rect::with_size(10, 20 + i as i32, 5, 1));
+            color
+        } else {
+            panic!();
+        };
+        assert_eq!(row(0), top);
+        assert_eq!(row(5), Rgb15::new(16, 15, 10));
+        assert_eq!(row(10), bottom);
+
+        let mut c = CaptureRenderer::new();
+        c.set_clip_rect(Rect::with_size(0, 25, 100, 2));
+        fill_gradient(&mut c, rect, top, bottom);
+        assert_eq!(c.ops.len(), 3);
+    }
+}
@@ -253,6 +253,10 @@ impl Canvas for CanvasImpl {
         &self.fonts
     }
 
+    fn clip_rect(&self) -> Rect {
+        self.clip_rect
+    }
+
     fn set_clip_rect(&mut self, rect: Rect) {
         self.reset_clip_rect();
         self.clip_rect = rect.intersect(self.clip_rect);
@@ -270,6 +274,22 @@ impl Canvas for CanvasImpl {
         }
     }
 
+    fn fill(&mut self, rect: Rect, color: Rgb15) {
+        let v = self.palette.color_idx(color);
+        let rect = rect
+            .intersect(Rect::with_size(0, 0, self.back_buf.width, self.back_buf.height))
+            .intersect(self.clip_rect);
+        if rect.is_empty() {
+            return;
+        }
+        for y in rect.top..rect.bottom {
+            let row = &mut self.back_buf.data[(y * self.back_buf.width) as usize..];
+            for b in &mut row[rect.left as usize..rect.right as usize] {
+                *b = v;
+            }
+        }
+    }
+
     fn draw(&mut self, tex: &TextureHandle, pos: Point, light: u32) {
         let pal = &self.palette;
         let tex = self.textures.get(tex);
@@ -282,6 +302,19 @@ impl Canvas for CanvasImpl {
         );
     }
 
+    fn draw_batch(&mut self, tex: &TextureHandle, sprites: &[(Point, u32)]) {
+        let pal = &self.palette;
+        let tex = self.textures.get(tex);
+        for &(pos, light) in sprites {
+            let light = (light >> 9) as u8;
+            Self::do_draw(&mut self.back_buf, pos.x, pos.y, &tex, self.clip_rect,
+                |dst, _, _, _, _, src| {
+                    *dst = pal.darken(src, light);
+                }
+            );
+        }
+    }
+
     fn draw_multi_light(&mut self, tex: &TextureHandle, pos: Point, lights: &[u32]) {
         let mut uniform = true;
         for i in 1..light_map::VERTEX_COUNT {
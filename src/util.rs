@@ -1,4 +1,5 @@
 pub mod array2d;
+pub mod lru;
 pub mod random;
 #[cfg(test)]
 pub mod test;
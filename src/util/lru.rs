@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Map that holds at most `capacity` entries evicting the least recently used entry when full.
+pub struct LruCache<K, V> {
+    capacity: usize,
+    entries: HashMap<K, (V, u64)>,
+    tick: u64,
+}
+
+impl<K: Copy + Eq + Hash, V: Clone> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0);
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            tick: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns `true` if there's entry for the `key`. Unlike `get()` doesn't affect the eviction
+    /// order.
+    pub fn contains_key(&self, key: K) -> bool {
+        self.entries.contains_key(&key)
+    }
+
+    pub fn get(&mut self, key: K) -> Option<V> {
+        self.tick += 1;
+        let tick = self.tick;
+        self.entries.get_mut(&key).map(|(v, t)| {
+            *t = tick;
+            v.clone()
+        })
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        self.tick += 1;
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            let lru = *self.entries.iter()
+                .min_by_key(|(_, (_, t))| *t)
+                .unwrap().0;
+            self.entries.remove(&lru);
+        }
+        self.entries.insert(key, (value, self.tick));
+    }
+
+    /// Returns cached value for `key` or inserts the one produced by `f`.
+    pub fn get_or_try_insert_with<E>(&mut self, key: K, f: impl FnOnce() -> Result<V, E>)
+        -> Result<V, E>
+    {
+        if let Some(v) = self.get(key) {
+            return Ok(v);
+        }
+        let v = f()?;
+        self.insert(key, v.clone());
+        Ok(v)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn evicts_lru() {
+        let mut c = LruCache::new(2);
+        let mut loads = 0;
+        let mut get = |c: &mut LruCache<u32, u32>, k| {
+            c.get_or_try_insert_with(k, || -> Result<_, ()> {
+                loads += 1;
+                Ok(k * 10)
+            }).unwrap()
+        };
+
+        assert_eq!(get(&mut c, 1), 10);
+        assert_eq!(get(&mut c, 2), 20);
+        assert_eq!(get(&mut c, 1), 10);
+        assert_eq!(get(&mut c, 3), 30);
+        assert_eq!(c.len(), 2);
+
+        // 2 was evicted and must be loaded again.
+        assert_eq!(get(&mut c, 2), 20);
+        assert_eq!(get(&mut c, 3), 30);
+        drop(get);
+        assert_eq!(loads, 4);
+    }
+}
@@ -18,6 +18,7 @@ use crate::graphics::{EPoint, Point};
 use crate::graphics::color::*;
 use crate::graphics::font::FontKey;
 use crate::graphics::geometry::hex::Direction;
+use crate::graphics::lighting;
 use crate::sequence::chain::Chain;
 use crate::util::random::{random as rand, RollCheckResult};
 
@@ -1130,19 +1131,7 @@ pub fn rm_timer_event(ctx: Context) -> Result<()> {
 pub fn set_light_level(ctx: Context) -> Result<()> {
     let v = cmp::min(cmp::max(ctx.prg.data_stack.pop()?.into_int()?, 0), 100) as u32;
 
-    const MIN: u32 = 0x4000;
-    const MID: u32 = 0xA000;
-    const MAX: u32 = 0x10000;
-
-    // TODO This probably should be fixed as follows:
-    // if v < 50 { MIN + v * (MID - MIN) / 50 } else { MID + (v - 50) * (MAX - MID) / 50 }
-    let light = match v {
-        0..=49 => MIN + v * (MID - MIN) / 100,
-        50 => MID,
-        _ => MID + v * (MAX - MID) / 100,
-    };
-
-    ctx.ext.world.ambient_light = light;
+    ctx.ext.world.ambient_light = lighting::light_level_to_intensity(v);
 
     log_a1!(ctx.prg, v);
 